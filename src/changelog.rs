@@ -0,0 +1,219 @@
+use std::{
+    fmt,
+    io::{self, BufRead},
+    str::FromStr,
+};
+
+use crate::transducer::{DiffEntry, Transducer};
+
+mod tests;
+
+/** One line of a change log:
+ * `+word,output` inserts a new word,
+ * `-word` removes a word and
+ * `=word,output` replaces the output of an existing word.
+ * The output is after the last comma, so words may contain commas */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Insert(String, i64),
+    Remove(String),
+    Replace(String, i64),
+}
+
+impl FromStr for Change {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let entry = |rest: &str| -> Result<(String, i64), String> {
+            let (word, output) = rest
+                .rsplit_once(',')
+                .ok_or_else(|| format!("{:?} has no output", rest))?;
+            let output = output
+                .trim()
+                .parse()
+                .map_err(|_| format!("{:?} is not a valid output", output))?;
+            return Ok((word.to_string(), output));
+        };
+
+        let change = match s.chars().next() {
+            Some('+') => entry(&s[1..]).map(|(w, o)| Change::Insert(w, o))?,
+            Some('-') => Change::Remove(s[1..].to_string()),
+            Some('=') => entry(&s[1..]).map(|(w, o)| Change::Replace(w, o))?,
+            _ => return Err(format!("{:?} does not start with +, - or =", s)),
+        };
+
+        if change.word().is_empty() {
+            return Err(String::from("the word is empty"));
+        }
+        return Ok(change);
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Insert(word, output) => write!(f, "+{},{}", word, output),
+            Change::Remove(word) => write!(f, "-{}", word),
+            Change::Replace(word, output) => write!(f, "={},{}", word, output),
+        }
+    }
+}
+
+/** Turns a difference between two transducers into the change that applies it to the old one */
+impl From<DiffEntry> for Change {
+    fn from(entry: DiffEntry) -> Self {
+        match entry {
+            DiffEntry::Added(word, output) => return Change::Insert(word, output),
+            DiffEntry::Removed(word, _) => return Change::Remove(word),
+            DiffEntry::Changed { word, new, .. } => return Change::Replace(word, new),
+        }
+    }
+}
+
+impl Change {
+    pub fn word(&self) -> &str {
+        match self {
+            Change::Insert(word, _) | Change::Remove(word) | Change::Replace(word, _) => {
+                return word;
+            }
+        }
+    }
+
+    /** Applies the change and returns whether it was applicable. Inserting an existing
+     * word, or removing or replacing a missing one, leaves the transducer unchanged, and so
+     * does a change which fails, because it would make an output overflow or because its
+     * output is not the rank of the word in rank mode */
+    pub fn apply(&self, transducer: &mut Transducer) -> Result<bool, String> {
+        match self {
+            Change::Insert(word, output) => {
+                if transducer.get(word).is_some() {
+                    return Ok(false);
+                }
+                check_rank(transducer, word, *output)?;
+                transducer
+                    .try_add_entry_out_of_order(word, *output)
+                    .map_err(|e| e.to_string())?;
+            }
+            Change::Remove(word) => {
+                let removed = transducer
+                    .try_remove_entry_with_word(word)
+                    .map_err(|e| e.to_string())?;
+                return Ok(removed.is_some());
+            }
+            Change::Replace(word, output) => {
+                if transducer.get(word).is_none() {
+                    return Ok(false);
+                }
+                check_rank(transducer, word, *output)?;
+                transducer
+                    .try_add_entry_out_of_order(word, *output)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        return Ok(true);
+    }
+}
+
+/** In rank mode the outputs are the ranks of the words, which add_entry_out_of_order assigns
+ * itself, so an entry can only be added or kept with the rank of its word */
+pub fn check_rank(transducer: &Transducer, word: &str, output: i64) -> Result<(), String> {
+    if !transducer.is_ranked() {
+        return Ok(());
+    }
+
+    let rank = transducer.rank_of(word);
+    if output != rank {
+        return Err(format!(
+            "the outputs are the ranks in rank mode, and {:?} has the rank {}",
+            word, rank
+        ));
+    }
+    return Ok(());
+}
+
+/** A line of a change log which could not be parsed or applied. Lines are 1-based */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ChangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "line {}: {}", self.line, self.message);
+    }
+}
+
+/** The outcome of applying a change log */
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChangeSummary {
+    pub inserted: usize,
+    pub removed: usize,
+    pub replaced: usize,
+    /** Changes which were not applicable, with their lines */
+    pub skipped: Vec<(usize, Change)>,
+    pub errors: Vec<ChangeError>,
+}
+
+impl fmt::Display for ChangeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "{} inserted, {} removed, {} replaced, {} skipped, {} errors",
+            self.inserted,
+            self.removed,
+            self.replaced,
+            self.skipped.len(),
+            self.errors.len()
+        );
+    }
+}
+
+/** Applies the changes of a change log in order. Blank lines are ignored. Invalid lines and
+ * changes which would make an output overflow are reported in the summary and do not stop the
+ * remaining changes */
+pub fn apply<R: BufRead>(transducer: &mut Transducer, reader: R) -> io::Result<ChangeSummary> {
+    let mut summary = ChangeSummary::default();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let change: Change = match line.parse() {
+            Ok(change) => change,
+            Err(message) => {
+                summary.errors.push(ChangeError {
+                    line: i + 1,
+                    message,
+                });
+                continue;
+            }
+        };
+
+        match change.apply(transducer) {
+            Ok(true) => {}
+            Ok(false) => {
+                summary.skipped.push((i + 1, change));
+                continue;
+            }
+            Err(message) => {
+                summary.errors.push(ChangeError {
+                    line: i + 1,
+                    message,
+                });
+                continue;
+            }
+        }
+
+        match change {
+            Change::Insert(..) => summary.inserted += 1,
+            Change::Remove(_) => summary.removed += 1,
+            Change::Replace(..) => summary.replaced += 1,
+        }
+    }
+
+    return Ok(summary);
+}
@@ -0,0 +1,182 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        changelog::{apply, Change, ChangeError, ChangeSummary},
+        transducer::{DiffEntry, Transducer},
+    };
+
+    fn example_transducer() -> Transducer {
+        return Transducer::from_dictionary(vec![
+            ("cab", 15),
+            ("cabab", 10),
+            ("cad", 8),
+            ("cbab", 3),
+        ]);
+    }
+
+    #[test]
+    fn parses_changes() {
+        assert_eq!(
+            "+cab,15".parse(),
+            Ok(Change::Insert(String::from("cab"), 15))
+        );
+        assert_eq!("-cab".parse(), Ok(Change::Remove(String::from("cab"))));
+        assert_eq!(
+            "=a,b, 7".parse(),
+            Ok(Change::Replace(String::from("a,b"), 7))
+        );
+        assert_eq!(
+            Change::Replace(String::from("a,b"), 7).to_string(),
+            "=a,b,7"
+        );
+
+        assert!("cab,15".parse::<Change>().is_err());
+        assert!("+cab".parse::<Change>().is_err());
+        assert!("+cab,x".parse::<Change>().is_err());
+        assert!("-".parse::<Change>().is_err());
+        assert!("=,3".parse::<Change>().is_err());
+    }
+
+    #[test]
+    fn applies_only_the_ranks_in_rank_mode() {
+        let mut transducer = Transducer::from_sorted_words(["cab", "cad", "cbab"]);
+        let log = "+caa,1\n+cac,1\n=cad,5\n=cad,2\n-cab\n";
+
+        let summary = apply(&mut transducer, log.as_bytes()).unwrap();
+
+        assert_eq!(summary.inserted, 1);
+        assert_eq!(summary.removed, 1);
+        assert_eq!(summary.replaced, 1);
+        assert_eq!(
+            summary.errors,
+            vec![
+                ChangeError {
+                    line: 1,
+                    message: String::from(
+                        "the outputs are the ranks in rank mode, and \"caa\" has the rank 0"
+                    ),
+                },
+                ChangeError {
+                    line: 3,
+                    message: String::from(
+                        "the outputs are the ranks in rank mode, and \"cad\" has the rank 2"
+                    ),
+                },
+            ]
+        );
+        assert_eq!(
+            transducer.entries().collect::<Vec<_>>(),
+            vec![
+                (String::from("cac"), 0),
+                (String::from("cad"), 1),
+                (String::from("cbab"), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn applies_a_change_log_in_order() {
+        let mut transducer = example_transducer();
+        let log = "+ca,9\n-cad\n=cab,1\n\n+cad,2\n-cad\n=cad,5\n+cab,4\n-x\n";
+
+        let summary = apply(&mut transducer, log.as_bytes()).unwrap();
+
+        assert_eq!(
+            summary,
+            ChangeSummary {
+                inserted: 2,
+                removed: 2,
+                replaced: 1,
+                skipped: vec![
+                    (7, Change::Replace(String::from("cad"), 5)),
+                    (8, Change::Insert(String::from("cab"), 4)),
+                    (9, Change::Remove(String::from("x"))),
+                ],
+                errors: vec![],
+            }
+        );
+        assert_eq!(
+            transducer.entries().collect::<Vec<_>>(),
+            vec![
+                (String::from("ca"), 9),
+                (String::from("cab"), 1),
+                (String::from("cabab"), 10),
+                (String::from("cbab"), 3),
+            ]
+        );
+        assert_eq!(transducer.validate(), Ok(()));
+        assert!(transducer.is_minimal());
+    }
+
+    #[test]
+    fn reports_invalid_lines_and_continues() {
+        let mut transducer = example_transducer();
+        let log = "+ca,9\r\n*cab\r\n+cb,x\r\n-cbab\r\n";
+
+        let summary = apply(&mut transducer, log.as_bytes()).unwrap();
+
+        assert_eq!(summary.inserted, 1);
+        assert_eq!(summary.removed, 1);
+        assert_eq!(
+            summary.errors.iter().map(|e| e.line).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        assert_eq!(
+            ChangeError {
+                line: 2,
+                message: String::from("oops")
+            }
+            .to_string(),
+            "line 2: oops"
+        );
+        assert_eq!(transducer.get("cbab"), None);
+        assert_eq!(transducer.get("ca"), Some(9));
+    }
+
+    #[test]
+    fn reports_changes_whose_outputs_overflow() {
+        let mut transducer = example_transducer();
+        let log = "+a,-9223372036854775808\n=cab,-9223372036854775797\n";
+
+        let summary = apply(&mut transducer, log.as_bytes()).unwrap();
+
+        assert_eq!(summary.inserted, 0);
+        assert_eq!(summary.replaced, 1);
+        assert_eq!(
+            summary.errors,
+            vec![ChangeError {
+                line: 1,
+                message: String::from(
+                    "the outputs overflow when \"a\" gets the output -9223372036854775808"
+                ),
+            }]
+        );
+        assert_eq!(transducer.get("a"), None);
+        assert_eq!(transducer.get("cab"), Some(-9223372036854775797));
+    }
+
+    #[test]
+    fn applies_the_diff_of_two_transducers() {
+        let old = example_transducer();
+        let new = Transducer::from_dictionary(vec![("ca", 9), ("cab", 1), ("cabab", 10)]);
+
+        let changes: Vec<Change> = Transducer::diff(&old, &new).map(Change::from).collect();
+        let log: String = changes.iter().map(|c| format!("{}\n", c)).collect();
+
+        assert_eq!(log, "+ca,9\n=cab,1\n-cad\n-cbab\n");
+        assert_eq!(
+            Change::from(DiffEntry::Changed {
+                word: String::from("cab"),
+                old: 15,
+                new: 1
+            }),
+            Change::Replace(String::from("cab"), 1)
+        );
+
+        let mut transducer = example_transducer();
+        let summary = apply(&mut transducer, log.as_bytes()).unwrap();
+
+        assert_eq!(summary.skipped, vec![]);
+        assert!(transducer == new);
+    }
+}
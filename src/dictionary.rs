@@ -0,0 +1,241 @@
+use std::{error::Error, fmt, fs::File, io, path::Path, str::FromStr};
+
+use crate::transducer::{BuildError, Builder, DuplicatePolicy, Transducer};
+
+mod tests;
+
+/** A column of a dictionary file, either by its 0-based index or by its header name */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(String::from("empty column name"));
+        }
+        return Ok(s
+            .parse()
+            .map_or_else(|_| Column::Name(s.to_string()), Column::Index));
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Column::Index(i) => write!(f, "{}", i),
+            Column::Name(name) => write!(f, "{:?}", name),
+        }
+    }
+}
+
+/** Invalid dictionary files. Lines are 1-based and count the header, if there is one */
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Csv {
+        line: Option<usize>,
+        message: String,
+    },
+    UnknownColumn(Column),
+    MissingField {
+        line: usize,
+        column: Column,
+    },
+    InvalidOutput {
+        line: usize,
+        value: String,
+    },
+    Build(BuildError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "{}", e),
+            LoadError::Csv {
+                line: Some(line),
+                message,
+            } => write!(f, "line {}: {}", line, message),
+            LoadError::Csv {
+                line: None,
+                message,
+            } => write!(f, "{}", message),
+            LoadError::UnknownColumn(column) => write!(f, "there is no column {}", column),
+            LoadError::MissingField { line, column } => {
+                write!(f, "line {}: there is no column {}", line, column)
+            }
+            LoadError::InvalidOutput { line, value } => {
+                write!(f, "line {}: {:?} is not a valid output", line, value)
+            }
+            LoadError::Build(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        return LoadError::Io(e);
+    }
+}
+
+impl From<csv::Error> for LoadError {
+    fn from(e: csv::Error) -> Self {
+        return LoadError::Csv {
+            line: e.position().map(|p| p.line() as usize),
+            message: e.to_string(),
+        };
+    }
+}
+
+/** Describes how to read a dictionary of (word, output) entries from a delimited text file.
+ * The default is a comma-separated file with a header and quoted fields, which is sorted */
+#[derive(Debug, Clone)]
+pub struct DictionaryFormat {
+    pub delimiter: u8,
+    pub has_header: bool,
+    pub word_column: Column,
+    pub output_column: Column,
+    /** Whether fields may be enclosed in quote characters */
+    pub quoting: bool,
+    pub quote: u8,
+    /** Sorted files are streamed into the transducer, buffered ones are sorted in memory */
+    pub sorted: bool,
+    /** With more than one thread the entries are read into memory and their key ranges
+     * are built in parallel */
+    pub threads: usize,
+}
+
+impl Default for DictionaryFormat {
+    fn default() -> Self {
+        return Self {
+            delimiter: b',',
+            has_header: true,
+            word_column: Column::Index(0),
+            output_column: Column::Index(1),
+            quoting: true,
+            quote: b'"',
+            sorted: true,
+            threads: 1,
+        };
+    }
+}
+
+impl DictionaryFormat {
+    /** A tab-separated file without a header or quoting, like resources/function.tsv */
+    pub fn tsv() -> Self {
+        return Self {
+            delimiter: b'\t',
+            has_header: false,
+            quoting: false,
+            ..Self::default()
+        };
+    }
+
+    /** Reads a dictionary file and constructs its minimal transducer */
+    pub fn read_path<P: AsRef<Path>>(
+        &self,
+        path: P,
+        policy: DuplicatePolicy,
+    ) -> Result<Transducer, LoadError> {
+        return self.read(File::open(path)?, policy);
+    }
+
+    /** Reads a dictionary and constructs its minimal transducer */
+    pub fn read<R: io::Read>(
+        &self,
+        reader: R,
+        policy: DuplicatePolicy,
+    ) -> Result<Transducer, LoadError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(self.has_header)
+            .quoting(self.quoting)
+            .quote(self.quote)
+            .flexible(true)
+            .from_reader(reader);
+
+        let headers = if self.has_header {
+            Some(reader.headers()?.clone())
+        } else {
+            None
+        };
+        let word_index = column_index(&self.word_column, headers.as_ref())?;
+        let output_index = column_index(&self.output_column, headers.as_ref())?;
+
+        let mut builder = Builder::new(policy);
+        let mut buffered: Vec<(String, i64, usize)> = Vec::new();
+        let mut record = csv::StringRecord::new();
+
+        while reader.read_record(&mut record)? {
+            let line = record.position().map_or(0, |p| p.line() as usize);
+            let field = |i: usize, column: &Column| {
+                record.get(i).ok_or_else(|| LoadError::MissingField {
+                    line,
+                    column: column.clone(),
+                })
+            };
+
+            let word = field(word_index, &self.word_column)?;
+            let output_field = field(output_index, &self.output_column)?;
+            let output = output_field
+                .trim()
+                .parse()
+                .map_err(|_| LoadError::InvalidOutput {
+                    line,
+                    value: output_field.to_string(),
+                })?;
+
+            if self.sorted && self.threads <= 1 {
+                builder
+                    .add(word, output)
+                    .map_err(|e| LoadError::Build(e.at_line(line)))?;
+            } else {
+                buffered.push((word.to_string(), output, line));
+            }
+        }
+
+        // The sort is stable, so duplicates keep the order of the file
+        if !self.sorted {
+            buffered.sort_by(|(w1, _, _), (w2, _, _)| w1.cmp(w2));
+        }
+
+        if self.threads > 1 {
+            let (entries, lines): (Vec<(String, i64)>, Vec<usize>) = buffered
+                .into_iter()
+                .map(|(word, output, line)| ((word, output), line))
+                .unzip();
+
+            return Transducer::try_from_sorted_slice_parallel(&entries, policy, self.threads)
+                .map_err(|e| match e.line() {
+                    Some(i) => LoadError::Build(e.at_line(lines[i - 1])),
+                    None => LoadError::Build(e),
+                });
+        }
+
+        for (word, output, line) in buffered {
+            builder
+                .add(&word, output)
+                .map_err(|e| LoadError::Build(e.at_line(line)))?;
+        }
+
+        return builder.finish().map_err(LoadError::Build);
+    }
+}
+
+fn column_index(column: &Column, headers: Option<&csv::StringRecord>) -> Result<usize, LoadError> {
+    match column {
+        Column::Index(i) => return Ok(*i),
+        Column::Name(name) => {
+            return headers
+                .and_then(|headers| headers.iter().position(|h| h == name))
+                .ok_or_else(|| LoadError::UnknownColumn(column.clone()));
+        }
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dictionary::{Column, DictionaryFormat, LoadError},
+        transducer::{BuildError, DuplicatePolicy},
+    };
+
+    #[test]
+    fn reads_a_tsv_dictionary() {
+        let input = "a\t117490\naaa\t117489\naaberg\t117488\n";
+        let transducer = DictionaryFormat::tsv()
+            .read(input.as_bytes(), DuplicatePolicy::Reject)
+            .unwrap();
+
+        assert_eq!(
+            transducer.entries().collect::<Vec<_>>(),
+            vec![
+                (String::from("a"), 117490),
+                (String::from("aaa"), 117489),
+                (String::from("aaberg"), 117488),
+            ]
+        );
+    }
+
+    #[test]
+    fn selects_columns_by_name_and_index() {
+        let input = "id;output;word\n1;15;cab\n2;\"8\";cad\n";
+        let format = DictionaryFormat {
+            delimiter: b';',
+            word_column: Column::Name(String::from("word")),
+            output_column: Column::Index(1),
+            ..DictionaryFormat::default()
+        };
+        let transducer = format
+            .read(input.as_bytes(), DuplicatePolicy::Reject)
+            .unwrap();
+
+        assert_eq!(transducer.get("cab"), Some(15));
+        assert_eq!(transducer.get("cad"), Some(8));
+    }
+
+    #[test]
+    fn keeps_quotes_without_quoting() {
+        let input = "\"cab\"\t15\n";
+        let format = DictionaryFormat {
+            quoting: false,
+            ..DictionaryFormat::tsv()
+        };
+        let transducer = format
+            .read(input.as_bytes(), DuplicatePolicy::Reject)
+            .unwrap();
+
+        assert_eq!(transducer.get("\"cab\""), Some(15));
+    }
+
+    #[test]
+    fn sorts_unsorted_dictionaries() {
+        let input = "word,output\ncbab,3\ncab,15\ncad,8\ncab,1\ncabab,10\n";
+        let format = DictionaryFormat {
+            sorted: false,
+            ..DictionaryFormat::default()
+        };
+        let transducer = format
+            .read(input.as_bytes(), DuplicatePolicy::KeepFirst)
+            .unwrap();
+
+        assert_eq!(transducer.get("cab"), Some(15));
+        assert_eq!(transducer.entries().count(), 4);
+        assert!(transducer.is_minimal());
+
+        let result = format.read(input.as_bytes(), DuplicatePolicy::Reject);
+        assert!(matches!(
+            result,
+            Err(LoadError::Build(BuildError::Duplicate { line: 5, .. }))
+        ));
+    }
+
+    #[test]
+    fn builds_in_parallel() {
+        let input = "word,output\ncab,15\ncabab,10\ncad,8\ncbab,3\ndab,13\nea,1\neb,1\n";
+        let format = DictionaryFormat {
+            threads: 3,
+            ..DictionaryFormat::default()
+        };
+        let transducer = format
+            .read(input.as_bytes(), DuplicatePolicy::Reject)
+            .unwrap();
+
+        assert_eq!(transducer.entries().count(), 7);
+        assert_eq!(transducer.get("dab"), Some(13));
+        assert!(transducer.is_minimal());
+
+        let input = "word,output\ncab,15\ncad,8\ndab,13\nca,1\n";
+        let result = format.read(input.as_bytes(), DuplicatePolicy::Reject);
+        assert!(matches!(
+            result,
+            Err(LoadError::Build(BuildError::OutOfOrder { line: 5, .. }))
+        ));
+    }
+
+    #[test]
+    fn reports_line_numbers() {
+        let format = DictionaryFormat::default();
+
+        let result = format.read(
+            "word,output\ncab,15\ncad,x\n".as_bytes(),
+            DuplicatePolicy::Reject,
+        );
+        assert!(matches!(
+            result,
+            Err(LoadError::InvalidOutput { line: 3, .. })
+        ));
+
+        let result = format.read(
+            "word,output\ncab,15\ncad\n".as_bytes(),
+            DuplicatePolicy::Reject,
+        );
+        assert!(matches!(
+            result,
+            Err(LoadError::MissingField { line: 3, .. })
+        ));
+
+        let result = format.read(
+            "word,output\ncad,15\ncab,8\n".as_bytes(),
+            DuplicatePolicy::Reject,
+        );
+        assert!(matches!(
+            result,
+            Err(LoadError::Build(BuildError::OutOfOrder { line: 3, .. }))
+        ));
+
+        let format = DictionaryFormat {
+            word_column: Column::Name(String::from("content")),
+            ..DictionaryFormat::default()
+        };
+        let result = format.read("word,output\ncab,15\n".as_bytes(), DuplicatePolicy::Reject);
+        assert!(matches!(result, Err(LoadError::UnknownColumn(_))));
+    }
+}
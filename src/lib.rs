@@ -0,0 +1,10 @@
+#![allow(
+    clippy::needless_return,
+    clippy::needless_range_loop,
+    clippy::module_inception,
+    clippy::useless_vec
+)]
+
+pub mod changelog;
+pub mod dictionary;
+pub mod transducer;
@@ -1,13 +1,34 @@
+#![allow(clippy::needless_return, clippy::module_inception)]
+
+use clap::{Args, Parser, Subcommand};
+use min_subseq_transducer::{
+    changelog,
+    dictionary::{Column, DictionaryFormat},
+    transducer::{ConflictPolicy, DuplicatePolicy, KeyEncoding, StringTransducer, Transducer},
+};
 use serde::Deserialize;
-use std::{error::Error, time::Instant};
+use std::{
+    collections::HashSet,
+    error::Error,
+    fs::File,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Instant,
+};
 
-mod transducer;
-use transducer::Transducer;
+mod repl;
 
 #[derive(Debug, Deserialize)]
 struct Record {
     word: String,
-    output: usize,
+    output: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rewrite {
+    word: String,
+    output: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -15,46 +36,499 @@ struct Word {
     content: String,
 }
 
-fn main() {
-    let mut transducer =
-        transducer_from_csv("/home/nakk/Workspace/uni/min-subseq-transducer/resources/add_all.csv").unwrap();
+/// Builds, queries and edits minimal subsequential transducers for dictionaries
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
 
-    println!("--------------------------");
+/// How a dictionary file is read
+#[derive(Args)]
+struct FormatArgs {
+    /// The field delimiter, a single character or "tab"
+    #[arg(long, default_value = ",", value_parser = parse_byte)]
+    delimiter: u8,
+    /// The first line is an entry and not a header
+    #[arg(long)]
+    no_header: bool,
+    /// The column of the words, by 0-based index or by header name
+    #[arg(long, default_value = "0")]
+    word_column: Column,
+    /// The column of the outputs, by 0-based index or by header name
+    #[arg(long, default_value = "1")]
+    output_column: Column,
+    /// Treats quote characters as part of the fields
+    #[arg(long)]
+    no_quoting: bool,
+    #[arg(long, default_value = "\"", value_parser = parse_byte)]
+    quote: u8,
+    /// Sorts the entries in memory instead of requiring a sorted file
+    #[arg(long)]
+    unsorted: bool,
+    /// What to do with repeated words: reject, keep-first, keep-last, min or sum
+    #[arg(long, default_value = "reject")]
+    duplicates: DuplicatePolicy,
+    /// Builds key ranges of the dictionary on this many threads
+    #[arg(long, default_value = "1")]
+    threads: usize,
+}
 
-    read_and_delete_entries(
-        &mut transducer,
-        "/home/nakk/Workspace/uni/min-subseq-transducer/resources/1-1.csv",
-    );
+impl FormatArgs {
+    /** Builds a transducer from the dictionary file at the path */
+    fn build(&self, input: &Path) -> Result<Transducer, Box<dyn Error>> {
+        let format = DictionaryFormat {
+            delimiter: self.delimiter,
+            has_header: !self.no_header,
+            word_column: self.word_column.clone(),
+            output_column: self.output_column.clone(),
+            quoting: !self.no_quoting,
+            quote: self.quote,
+            sorted: !self.unsorted,
+            threads: self.threads,
+        };
 
-    read_and_delete_entries(
-        &mut transducer,
-        "/home/nakk/Workspace/uni/min-subseq-transducer/resources/1-2.csv",
-    );
-    // TODO!: Cannot delete the last word for now
+        let now = Instant::now();
+        let transducer = format.read_path(input, self.duplicates)?;
+        eprintln!("Done building in {:?}", now.elapsed());
+        return Ok(transducer);
+    }
 }
 
-fn read_and_delete_entries(transducer: &mut Transducer, file_name: &str) {
-    let csv_result = read_csv2(file_name);
+#[derive(Subcommand)]
+enum Command {
+    /// Builds a transducer from a dictionary file and saves it
+    Build {
+        input: PathBuf,
+        transducer: PathBuf,
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    /// Prints the output of every given word
+    Lookup {
+        transducer: PathBuf,
+        #[arg(required = true)]
+        words: Vec<String>,
+    },
+    /// Adds an entry, or the entries of a `word,output` CSV file, in any order
+    Add {
+        transducer: PathBuf,
+        #[arg(requires = "output", required_unless_present = "csv")]
+        word: Option<String>,
+        #[arg(allow_negative_numbers = true)]
+        output: Option<i64>,
+        #[arg(long, conflicts_with = "word")]
+        csv: Option<PathBuf>,
+        /// Saves the result here instead of overwriting the transducer
+        #[arg(long)]
+        save_to: Option<PathBuf>,
+    },
+    /// Removes the given words, or the words of a `content` CSV file
+    Remove {
+        transducer: PathBuf,
+        #[arg(required_unless_present = "csv")]
+        words: Vec<String>,
+        #[arg(long, conflicts_with = "words")]
+        csv: Option<PathBuf>,
+        /// Saves the result here instead of overwriting the transducer
+        #[arg(long)]
+        save_to: Option<PathBuf>,
+    },
+    /// Applies a change log of `+word,output`, `-word` and `=word,output` lines in order
+    Apply {
+        transducer: PathBuf,
+        changes: PathBuf,
+        /// Saves the result here instead of overwriting the transducer
+        #[arg(long)]
+        save_to: Option<PathBuf>,
+    },
+    /// Combines the entries of two transducers into a new one
+    Union {
+        left: PathBuf,
+        right: PathBuf,
+        transducer: PathBuf,
+        /// The output of words in both: left, right, min, max or sum
+        #[arg(long, default_value = "left")]
+        conflicts: ConflictPolicy,
+    },
+    /// Keeps the words that are in both transducers
+    Intersection {
+        left: PathBuf,
+        right: PathBuf,
+        transducer: PathBuf,
+        /// The output of the words: left, right, min, max or sum
+        #[arg(long, default_value = "left")]
+        conflicts: ConflictPolicy,
+    },
+    /// Keeps the entries of the left transducer, whose words are not in the right one
+    Difference {
+        left: PathBuf,
+        right: PathBuf,
+        transducer: PathBuf,
+    },
+    /// Maps the words of a `word,output` CSV file of word pairs, e.g. surface forms and lemmas,
+    /// directly to the outputs their outputs have in a transducer
+    Compose {
+        pairs: PathBuf,
+        lookup: PathBuf,
+        transducer: PathBuf,
+    },
+    /// Maps the decimal output of every word back to its rank in lexicographic order
+    Invert {
+        input: PathBuf,
+        transducer: PathBuf,
+        /// Uses this many big-endian bytes of the outputs as keys instead of decimal digits
+        #[arg(long)]
+        fixed_width: Option<usize>,
+    },
+    /// Checks if two transducers define the same function, or prints a word where they differ
+    Compare { left: PathBuf, right: PathBuf },
+    /// Prints the change log that turns the old transducer into the new one
+    Diff { old: PathBuf, new: PathBuf },
+    /// Prints all entries as a sorted `word,output` CSV file
+    Dump { transducer: PathBuf },
+    /// Prints the number of states, transitions and final states
+    Stats {
+        transducer: PathBuf,
+        /// Also prints the bytes used by each internal structure
+        #[arg(long)]
+        memory: bool,
+    },
+    /// Checks the internal invariants and the minimality of a transducer
+    Validate { transducer: PathBuf },
+    /// Starts an interactive session for querying and editing a transducer
+    Repl {
+        /// The saved transducer, or the dictionary file with --dictionary
+        transducer: PathBuf,
+        /// Builds the transducer from a dictionary file, read like by build, instead of loading it
+        #[arg(long)]
+        dictionary: bool,
+        #[command(flatten)]
+        format: FormatArgs,
+        /// Saves the result here on exit
+        #[arg(long)]
+        save_to: Option<PathBuf>,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli.command) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Found an error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<ExitCode, Box<dyn Error>> {
+    match command {
+        Command::Build {
+            input,
+            transducer: path,
+            format,
+        } => {
+            let transducer = format.build(&input)?;
+            save(&transducer, &path)?;
+        }
+        Command::Lookup {
+            transducer: path,
+            words,
+        } => {
+            let transducer = load(&path)?;
+            let mut found_all = true;
+
+            for word in words {
+                match transducer.get(&word) {
+                    Some(output) => println!("{}\t{}", word, output),
+                    None => {
+                        println!("{}\t-", word);
+                        found_all = false;
+                    }
+                }
+            }
+
+            if !found_all {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Add {
+            transducer: path,
+            word,
+            output,
+            csv,
+            save_to,
+        } => {
+            let mut transducer = load(&path)?;
+
+            match (word, output, csv) {
+                (Some(word), Some(output), _) => {
+                    check_word(&word)?;
+                    transducer.try_add_entry_out_of_order(&word, output)?
+                }
+                (_, _, Some(csv)) => read_and_add_entries(&mut transducer, &csv)?,
+                _ => unreachable!("clap requires a word and an output or a csv file"),
+            }
+
+            save(&transducer, save_to.as_ref().unwrap_or(&path))?;
+        }
+        Command::Remove {
+            transducer: path,
+            words,
+            csv,
+            save_to,
+        } => {
+            let mut transducer = load(&path)?;
+
+            match csv {
+                Some(csv) => read_and_delete_entries(&mut transducer, &csv)?,
+                None => delete_entries(&mut transducer, words)?,
+            }
+
+            save(&transducer, save_to.as_ref().unwrap_or(&path))?;
+        }
+        Command::Apply {
+            transducer: path,
+            changes,
+            save_to,
+        } => {
+            let mut transducer = load(&path)?;
 
-    match csv_result {
-        Ok(records) => {
             let now = Instant::now();
+            let summary = changelog::apply(&mut transducer, BufReader::new(File::open(&changes)?))?;
+            eprintln!("Done applying in {:?}", now.elapsed());
+
+            for (line, change) in &summary.skipped {
+                eprintln!("Skipping line {}: {} is not applicable", line, change);
+            }
+            for error in &summary.errors {
+                eprintln!("Found an error: {}", error);
+            }
+            eprintln!("{}", summary);
+
+            save(&transducer, save_to.as_ref().unwrap_or(&path))?;
+
+            if !summary.errors.is_empty() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Union {
+            left,
+            right,
+            transducer: path,
+            conflicts,
+        } => {
+            let (left, right) = (load(&left)?, load(&right)?);
+
+            let now = Instant::now();
+            let union = Transducer::union(&left, &right, conflicts);
+            eprintln!("Done uniting in {:?}", now.elapsed());
+
+            save(&union, &path)?;
+        }
+        Command::Intersection {
+            left,
+            right,
+            transducer: path,
+            conflicts,
+        } => {
+            let (left, right) = (load(&left)?, load(&right)?);
+
+            let now = Instant::now();
+            let intersection =
+                Transducer::intersection(&left, &right, |l, r| conflicts.combine(l, r));
+            eprintln!("Done intersecting in {:?}", now.elapsed());
+
+            save(&intersection, &path)?;
+        }
+        Command::Difference {
+            left,
+            right,
+            transducer: path,
+        } => {
+            let (left, right) = (load(&left)?, load(&right)?);
+
+            let now = Instant::now();
+            let difference = Transducer::difference(&left, &right);
+            eprintln!("Done subtracting in {:?}", now.elapsed());
+
+            save(&difference, &path)?;
+        }
+        Command::Compose {
+            pairs,
+            lookup,
+            transducer: path,
+        } => {
+            let mut rewrites = Vec::new();
+            for record in csv::Reader::from_path(&pairs)?.deserialize() {
+                let record: Rewrite = record?;
+                rewrites.push((record.word, record.output));
+            }
+            let lookup = load(&lookup)?;
+
+            let now = Instant::now();
+            let composition =
+                Transducer::compose(&StringTransducer::from_pairs(rewrites), &lookup)?;
+            eprintln!("Done composing in {:?}", now.elapsed());
+
+            save(&composition, &path)?;
+        }
+        Command::Invert {
+            input,
+            transducer: path,
+            fixed_width,
+        } => {
+            let transducer = load(&input)?;
+            let encoding = fixed_width.map_or(KeyEncoding::Decimal, KeyEncoding::FixedWidth);
+
+            let now = Instant::now();
+            let inverse = transducer.invert(encoding)?;
+            eprintln!("Done inverting in {:?}", now.elapsed());
+
+            save(&inverse, &path)?;
+        }
+        Command::Compare { left, right } => {
+            let (left, right) = (load(&left)?, load(&right)?);
 
-            for w in records {
-                transducer.remove_entry_with_word(w.content.as_str());
+            if let Some(word) = left.counterexample(&right) {
+                let show =
+                    |output: Option<i64>| output.map_or(String::from("-"), |o| o.to_string());
+                println!(
+                    "{}\t{}\t{}",
+                    word,
+                    show(left.get(&word)),
+                    show(right.get(&word))
+                );
+                return Ok(ExitCode::FAILURE);
             }
 
-            println!("Done deleting in {:?}", now.elapsed());
+            println!("The transducers are equivalent");
+        }
+        Command::Diff { old, new } => {
+            let (old, new) = (load(&old)?, load(&new)?);
+
+            for entry in Transducer::diff(&old, &new) {
+                println!("{}", changelog::Change::from(entry));
+            }
+        }
+        Command::Dump { transducer: path } => {
+            let transducer = load(&path)?;
+            let mut writer = csv::Writer::from_writer(io::stdout().lock());
 
+            writer.write_record(["word", "output"])?;
+            for (word, output) in transducer.entries() {
+                writer.write_record([word, output.to_string()])?;
+            }
+            writer.flush()?;
+        }
+        Command::Stats {
+            transducer: path,
+            memory,
+        } => {
+            let transducer = load(&path)?;
             transducer.print();
+
+            if memory {
+                println!("{}", transducer.memory_stats());
+            }
         }
-        Err(e) => {
-            println!("Found an error: {:?}", e);
+        Command::Validate { transducer: path } => {
+            let transducer = load(&path)?;
+
+            if let Err(violations) = transducer.validate() {
+                for violation in violations {
+                    println!("{}", violation);
+                }
+                return Ok(ExitCode::FAILURE);
+            }
+
+            if !transducer.is_minimal() {
+                println!("The transducer is not minimal");
+                return Ok(ExitCode::FAILURE);
+            }
+
+            println!("The transducer is valid");
         }
+        Command::Repl {
+            transducer: path,
+            dictionary,
+            format,
+            save_to,
+        } => {
+            let transducer = match dictionary {
+                true => format.build(&path)?,
+                false => load(&path)?,
+            };
+            let transducer = repl::run(transducer, BufReader::new(io::stdin()), io::stdout())?;
+
+            if let Some(save_to) = save_to {
+                save(&transducer, &save_to)?;
+            }
+        }
+    }
+
+    return Ok(ExitCode::SUCCESS);
+}
+
+/** Parses a single ASCII character, or "tab" */
+fn parse_byte(s: &str) -> Result<u8, String> {
+    match s {
+        "tab" | "\\t" => Ok(b'\t'),
+        _ if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+        _ => Err(format!("{:?} is not a single ASCII character", s)),
+    }
+}
+
+/** Rejects the empty word, which the transducer cannot take as input */
+fn check_word(word: &str) -> Result<(), Box<dyn Error>> {
+    if word.is_empty() {
+        return Err("the transducer cannot take epsilon as input".into());
+    }
+    return Ok(());
+}
+
+fn load(path: &Path) -> Result<Transducer, Box<dyn Error>> {
+    let now = Instant::now();
+    let transducer = Transducer::load(File::open(path)?)?;
+    eprintln!("Done loading in {:?}", now.elapsed());
+    return Ok(transducer);
+}
+
+fn save(transducer: &Transducer, path: &Path) -> Result<(), Box<dyn Error>> {
+    let now = Instant::now();
+    transducer.save(File::create(path)?)?;
+    eprintln!("Done saving in {:?}", now.elapsed());
+    return Ok(());
+}
+
+fn read_and_delete_entries(
+    transducer: &mut Transducer,
+    file_name: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let records = read_csv2(file_name)?;
+    return delete_entries(transducer, records.into_iter().map(|w| w.content).collect());
+}
+
+fn delete_entries(transducer: &mut Transducer, words: Vec<String>) -> Result<(), Box<dyn Error>> {
+    for word in &words {
+        check_word(word)?;
     }
+    let now = Instant::now();
+    let removed = transducer.try_remove_batch(&words)?;
+    let removed: HashSet<&str> = removed.iter().map(|(w, _)| w.as_str()).collect();
+
+    for word in words.iter().filter(|w| !removed.contains(w.as_str())) {
+        eprintln!("Skipping {:?}, it is not in the transducer", word);
+    }
+
+    eprintln!("Done deleting in {:?}", now.elapsed());
+    return Ok(());
 }
 
-fn read_csv2(file_name: &str) -> Result<Vec<Word>, Box<dyn Error>> {
+fn read_csv2(file_name: &Path) -> Result<Vec<Word>, Box<dyn Error>> {
     let mut results: Vec<Word> = Vec::new();
     let reader = csv::Reader::from_path(file_name);
 
@@ -66,7 +540,7 @@ fn read_csv2(file_name: &str) -> Result<Vec<Word>, Box<dyn Error>> {
     Ok(results)
 }
 
-fn read_csv(file_name: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+fn read_csv(file_name: &Path) -> Result<Vec<Record>, Box<dyn Error>> {
     let mut results: Vec<Record> = Vec::new();
     let reader = csv::Reader::from_path(file_name);
 
@@ -78,50 +552,18 @@ fn read_csv(file_name: &str) -> Result<Vec<Record>, Box<dyn Error>> {
     Ok(results)
 }
 
-fn transducer_from_csv(file_name: &str) -> Option<Transducer> {
-    let csv_result = read_csv(file_name);
-
-    match csv_result {
-        Ok(records) => {
-            let dictionary: Vec<(&str, usize)> = records
-                .iter()
-                .map(|r| (r.word.as_str(), r.output))
-                .collect();
-
-            let now = Instant::now();
-
-            let transducer = Transducer::from_dictionary(dictionary);
-
-            println!("Done building in {:?}", now.elapsed());
-
-            transducer.print();
-
-            return Some(transducer);
-        }
-        Err(e) => {
-            println!("Found an error: {:?}", e);
-            return None;
-        }
+fn read_and_add_entries(
+    transducer: &mut Transducer,
+    file_name: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let records = read_csv(file_name)?;
+    for record in &records {
+        check_word(&record.word)?;
     }
-}
-
-fn read_and_add_entries(transducer: &mut Transducer, file_name: &str) {
-    let csv_result = read_csv(file_name);
-
-    match csv_result {
-        Ok(records) => {
-            let now = Instant::now();
+    let now = Instant::now();
 
-            for record in records {
-                transducer.add_entry_out_of_order(record.word.as_str(), record.output);
-            }
-
-            println!("Done adding in {:?}", now.elapsed());
+    transducer.try_insert_batch(records.into_iter().map(|r| (r.word, r.output)))?;
 
-            transducer.print();
-        }
-        Err(e) => {
-            println!("Found an error: {:?}", e);
-        }
-    }
+    eprintln!("Done adding in {:?}", now.elapsed());
+    return Ok(());
 }
@@ -0,0 +1,180 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, Write},
+    time::Instant,
+};
+
+use min_subseq_transducer::{changelog, transducer::Transducer};
+
+mod tests;
+
+const HELP: &str = "\
+get WORD...        prints the outputs of the words
+prefix PREFIX      prints all entries starting with the prefix
+add WORD OUTPUT    adds an entry or replaces its output
+del WORD           removes an entry
+stats              prints the number of states, transitions and final states
+compact            renumbers the states densely, releasing the ids of deleted states
+dot                prints the transducer in the Graphviz DOT language
+undo               reverts the last add or del
+save PATH          saves the transducer
+help               prints this message
+quit               exits";
+
+/** An applied edit, remembered so that it can be reverted */
+enum Edit {
+    Added { word: String, previous: Option<i64> },
+    Removed { word: String, output: i64 },
+}
+
+/** Reads commands line by line and applies them to the transducer until quit or the end of
+ * the input. Returns the transducer with all edits applied */
+pub fn run<R: BufRead, W: Write>(
+    mut transducer: Transducer,
+    input: R,
+    mut output: W,
+) -> io::Result<Transducer> {
+    let mut history: Vec<Edit> = Vec::new();
+
+    write!(output, "> ")?;
+    output.flush()?;
+
+    for line in input.lines() {
+        let line = line?;
+        let args: Vec<&str> = line.split_whitespace().collect();
+
+        match args.as_slice() {
+            [] => {}
+            ["quit" | "exit"] => break,
+            ["help"] => writeln!(output, "{}", HELP)?,
+            ["get", words @ ..] if !words.is_empty() => {
+                for word in words {
+                    match transducer.get(word) {
+                        Some(o) => writeln!(output, "{}\t{}", word, o)?,
+                        None => writeln!(output, "{}\t-", word)?,
+                    }
+                }
+            }
+            ["prefix", prefix] => {
+                let now = Instant::now();
+                let mut n = 0;
+
+                for (word, o) in transducer.entries_with_prefix(prefix) {
+                    writeln!(output, "{}\t{}", word, o)?;
+                    n += 1;
+                }
+
+                writeln!(output, "Found {} entries in {:?}", n, now.elapsed())?;
+            }
+            ["add", word, o] => match o.parse::<i64>() {
+                Ok(o) => match changelog::check_rank(&transducer, word, o) {
+                    Ok(()) => {
+                        let previous = transducer.get(word);
+                        let now = Instant::now();
+
+                        match transducer.try_add_entry_out_of_order(word, o) {
+                            Ok(()) => {
+                                writeln!(output, "Done adding in {:?}", now.elapsed())?;
+                                history.push(Edit::Added {
+                                    word: word.to_string(),
+                                    previous,
+                                });
+                            }
+                            Err(e) => writeln!(output, "Cannot add {}: {}", word, e)?,
+                        }
+                    }
+                    Err(e) => writeln!(output, "Cannot add {}: {}", word, e)?,
+                },
+                Err(_) => writeln!(output, "{:?} is not a valid output", o)?,
+            },
+            ["del", word] => {
+                let now = Instant::now();
+
+                match transducer.try_remove_entry_with_word(word) {
+                    Ok(Some(o)) => {
+                        writeln!(output, "Done deleting in {:?}", now.elapsed())?;
+                        history.push(Edit::Removed {
+                            word: word.to_string(),
+                            output: o,
+                        });
+                    }
+                    Ok(None) => writeln!(output, "{} is not in the transducer", word)?,
+                    Err(e) => writeln!(output, "Cannot delete {}: {}", word, e)?,
+                }
+            }
+            ["undo"] => match history.pop() {
+                Some(edit) => {
+                    let now = Instant::now();
+                    undo(&mut transducer, edit);
+                    writeln!(output, "Done undoing in {:?}", now.elapsed())?;
+                }
+                None => writeln!(output, "Nothing to undo")?,
+            },
+            ["stats"] => {
+                writeln!(
+                    output,
+                    "Number of states: {:?}",
+                    transducer.get_number_of_states()
+                )?;
+                writeln!(
+                    output,
+                    "Number of transitions: {:?}",
+                    transducer.get_number_of_transitions()
+                )?;
+                writeln!(
+                    output,
+                    "Initial output: {:?}",
+                    transducer.get_initial_output()
+                )?;
+                writeln!(
+                    output,
+                    "Number of final states: {:?}",
+                    transducer.get_number_of_final_states()
+                )?;
+                writeln!(
+                    output,
+                    "Number of released ids: {:?}",
+                    transducer.get_number_of_released_ids()
+                )?;
+            }
+            ["compact"] => {
+                let now = Instant::now();
+                transducer.compact();
+                writeln!(output, "Done compacting in {:?}", now.elapsed())?;
+            }
+            ["dot"] => write!(output, "{}", transducer.to_dot())?,
+            ["save", path] => {
+                let now = Instant::now();
+
+                match File::create(path).and_then(|file| transducer.save(file)) {
+                    Ok(()) => writeln!(output, "Done saving in {:?}", now.elapsed())?,
+                    Err(e) => writeln!(output, "Found an error: {}", e)?,
+                }
+            }
+            _ => writeln!(output, "Unknown command {:?}, try help", line.trim())?,
+        }
+
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+
+    writeln!(output)?;
+    return Ok(transducer);
+}
+
+/** Reverts an edit with the inverse add or del */
+fn undo(transducer: &mut Transducer, edit: Edit) {
+    match edit {
+        Edit::Added {
+            word,
+            previous: Some(o),
+        } => transducer.add_entry_out_of_order(&word, o),
+        Edit::Added {
+            word,
+            previous: None,
+        } => {
+            transducer.remove_entry_with_word(&word);
+        }
+        Edit::Removed { word, output } => transducer.add_entry_out_of_order(&word, output),
+    }
+}
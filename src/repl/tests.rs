@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use min_subseq_transducer::transducer::Transducer;
+
+    use crate::repl::run;
+
+    fn run_commands(commands: &str) -> (Transducer, String) {
+        let transducer =
+            Transducer::from_dictionary(vec![("cab", 15), ("cabab", 10), ("cad", 8), ("cbab", 3)]);
+        let mut output = Vec::new();
+        let transducer = run(transducer, commands.as_bytes(), &mut output).unwrap();
+        return (transducer, String::from_utf8(output).unwrap());
+    }
+
+    #[test]
+    fn queries_the_transducer() {
+        let (_, output) = run_commands("get cab ca\nprefix cab\n");
+
+        assert!(output.contains("cab\t15\n"));
+        assert!(output.contains("ca\t-\n"));
+        assert!(output.contains("cab\t15\ncabab\t10\nFound 2 entries"));
+    }
+
+    #[test]
+    fn edits_and_undoes() {
+        let (transducer, output) =
+            run_commands("add ca 9\nadd cab 1\ndel cad\ndel cad\nundo\nundo\n");
+
+        assert!(output.contains("cad is not in the transducer"));
+        assert_eq!(transducer.get("ca"), Some(9));
+        assert_eq!(transducer.get("cab"), Some(15));
+        assert_eq!(transducer.get("cad"), Some(8));
+        assert_eq!(transducer.validate(), Ok(()));
+
+        let (transducer, _) = run_commands("add ca 9\nundo\nundo\n");
+        assert_eq!(transducer.get("ca"), None);
+        assert!(transducer.is_minimal());
+    }
+
+    #[test]
+    fn adds_only_the_ranks_in_rank_mode() {
+        let transducer = Transducer::from_sorted_words(["cab", "cad"]);
+        let mut output = Vec::new();
+        let commands = "add cac 2\nadd cac 1\nadd cab 3\n";
+        let transducer = run(transducer, commands.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("Cannot add cac: the outputs are the ranks in rank mode, and"));
+        assert!(output.contains("Cannot add cab: "));
+        assert_eq!(transducer.get("cac"), Some(1));
+        assert_eq!(transducer.get("cad"), Some(2));
+    }
+
+    #[test]
+    fn compacts_the_states() {
+        let (transducer, output) = run_commands("del cbab\ncompact\nstats\n");
+
+        assert!(output.contains("Done compacting"));
+        assert!(output.contains("Number of released ids: 0"));
+        assert_eq!(transducer.get_number_of_released_ids(), 0);
+        assert_eq!(transducer.validate(), Ok(()));
+    }
+
+    #[test]
+    fn stops_at_quit() {
+        let (transducer, output) = run_commands("add x 1\nquit\nadd y 2\nfoo\n");
+
+        assert_eq!(transducer.get("x"), Some(1));
+        assert_eq!(transducer.get("y"), None);
+        assert!(!output.contains("Unknown command"));
+    }
+}
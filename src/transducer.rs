@@ -1,44 +1,84 @@
 use std::{
     cmp::{max, min},
-    collections::{BTreeSet, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashSet},
     panic,
 };
 
+mod builder;
+mod compact;
+mod compose;
+mod diff;
+mod differential_tests;
+mod dot;
+mod entries;
+mod equivalence;
+mod invariants;
+mod invert;
+mod memory;
+mod minimise;
+mod overflow;
+mod parallel;
+mod product;
+mod rank;
+mod register;
+mod serialise;
+mod state;
 mod tests;
 mod utils;
-use utils::{add_to_or_insert, longest_common_prefix, remove_from_or_delete};
+pub use builder::{BuildError, Builder, DuplicatePolicy};
+pub use compose::{CompositionError, StringTransducer};
+pub use diff::{Diff, DiffEntry};
+pub use entries::Entries;
+pub use invariants::InvariantViolation;
+pub use invert::{InversionError, KeyEncoding};
+pub use memory::MemoryStats;
+pub use overflow::OverflowError;
+use overflow::{difference, narrow, sum};
+pub use product::ConflictPolicy;
+use register::Register;
+use state::{State, Transition};
+use utils::longest_common_prefix;
 
 pub struct Transducer {
     alphabet: HashSet<char>,
-    states: BTreeSet<usize>,
-    finality: BTreeSet<usize>,
+    /** The arena of states, indexed by their ids. The ids released by delete_state are None */
+    states: Vec<Option<State>>,
+    /** The released ids, which are reused for new states */
+    free: Vec<usize>,
     init_state: usize,
-    delta: HashMap<usize, HashMap<char, usize>>,
-    delta_inv: HashMap<usize, HashSet<(char, usize)>>,
-    lambda: HashMap<usize, HashMap<char, usize>>,
-    iota: usize,
-    psi: HashMap<usize, usize>,
+    iota: i64,
     min_except: Vec<char>,
-    states_by_signature: HashMap<(Option<usize>, BTreeSet<(char, usize, usize)>), usize>,
+    /** The states outside of min_except, by their signature */
+    register: Register,
+    /** The maximal share of released ids, above which the states are compacted automatically */
+    compaction_threshold: Option<f64>,
+    /** Whether the outputs are the lexicographic ranks of the words, which edits keep dense */
+    ranked: bool,
 }
 
 impl Transducer {
-    pub fn get_states(&self) -> &BTreeSet<usize> {
-        return &self.states;
+    pub fn get_number_of_states(&self) -> usize {
+        return self.states.len() - self.free.len();
     }
 
-    pub fn get_finality(&self) -> &BTreeSet<usize> {
-        return &self.finality;
+    pub fn get_number_of_final_states(&self) -> usize {
+        return self.live_states().filter(|(_, s)| s.is_final()).count();
     }
 
-    pub fn get_initial_output(&self) -> usize {
+    pub fn get_initial_output(&self) -> i64 {
         return self.iota;
     }
 
     /** Adds a new entry to the transducer,
-     * that is lexicographically greater than the last added entry*/
-    pub fn add_entry_in_order(&mut self, word: &str, output: usize) {
+     * that is lexicographically greater than the last added entry.
+     * Panics if an output overflows, which `Builder` checks beforehand */
+    pub fn add_entry_in_order(&mut self, word: &str, output: i64) {
         let word: Vec<char> = word.chars().collect();
+
+        if word < self.min_except {
+            panic!("Entries must be added in lexicographic order!");
+        }
+
         let n = word.len();
         let k = longest_common_prefix(&self.min_except, &word).len();
 
@@ -52,68 +92,62 @@ impl Transducer {
 
         // Add the transitions for the missing suffix
         for i in 1..(n - k) {
-            self.add_delta_transition(tkn[i - 1], word[k + i], tkn[i]);
+            self.add_delta_transition(tkn[i - 1], word[k + i], tkn[i], 0);
         }
 
         // Make the last state of word final
-        self.finality.insert(*tkn.last().unwrap_or(&tk));
+        let tn = *tkn.last().unwrap_or(&tk);
+        self.state_mut(tn).final_output.get_or_insert(0);
 
         // Add a transition from the existing prefix
         if n - k > 0 {
-            self.add_delta_transition(tk, word[k], tkn[0]);
+            self.add_delta_transition(tk, word[k], tkn[0], 0);
         }
 
         let word_states = self.state_sequence(&word);
 
         // Update final outputs
         for i in 1..=k {
-            if self.finality.contains(&word_states[i]) {
-                let final_output = self.output(&word[..i].to_vec()) - self.lambda_i(i, output);
-                self.psi.insert(word_states[i], final_output);
+            if self.is_final(word_states[i]) {
+                let final_output =
+                    difference(self.output(&word[..i].to_vec()), self.lambda_i(i, output));
+                self.state_mut(word_states[i]).final_output = Some(final_output);
             }
         }
-        word_states.last().and_then(|tm| self.psi.insert(*tm, 0));
+        self.state_mut(tn).final_output = Some(0);
 
         // Update output transitions
         //
         // NOTE: the first and last updates of lambda both depend on the old lambda.
         // This means that the updates have to be done simultaneously.
-        let mut postponed_lambda_updates: Vec<(usize, char, usize)> = Vec::new();
+        let mut postponed_lambda_updates: Vec<(usize, char, i64)> = Vec::new();
         for i in 1..=k {
             let curr_output = self.lambda_i(i, output);
             let prev_output = self.lambda_i(i - 1, output);
             postponed_lambda_updates.push((
                 word_states[i - 1],
                 self.min_except[i - 1],
-                curr_output - prev_output,
+                difference(curr_output, prev_output),
             ));
         }
 
         if n - k > 0 {
             let lambda_k = self.lambda_i(k, output);
-            add_to_or_insert(&mut self.lambda, word_states[k], word[k], output - lambda_k);
-        }
-
-        for i in 1..(n - k) {
-            add_to_or_insert(&mut self.lambda, tkn[i - 1], word[k + i], 0);
+            self.set_output(word_states[k], word[k], difference(output, lambda_k));
         }
 
         for i in 0..=k {
-            for ch in self.alphabet.iter() {
-                let is_lambda_defined = self
-                    .lambda
-                    .get(&word_states[i])
-                    .and_then(|trans| trans.get(ch))
-                    .is_some();
-
-                if i < word.len() && *ch != word[i] && is_lambda_defined {
+            for t in &self.state(word_states[i]).transitions {
+                if i < word.len() && t.ch != word[i] {
                     let mut prefix_with_ch = word[0..i].to_vec();
-                    prefix_with_ch.push(*ch);
+                    prefix_with_ch.push(t.ch);
 
-                    let output =
-                        self.iota + self.lambda_star(&prefix_with_ch) - self.lambda_i(i, output);
+                    let output = difference(
+                        self.prefix_output(&prefix_with_ch),
+                        self.lambda_i(i, output),
+                    );
 
-                    postponed_lambda_updates.push((word_states[i], *ch, output));
+                    postponed_lambda_updates.push((word_states[i], t.ch, output));
                 }
             }
         }
@@ -124,34 +158,23 @@ impl Transducer {
         //
         // NOTE: This is neccessary because add_entry_in_order is used in add_entry_out_of_order.
         // Which means that the added word may be a prefix of some exisiting word in the transducer
-        let tn = word_states.last().expect("Idk at this point; TODO");
-        for ch in self.alphabet.iter() {
-            let is_lambda_defined = self
-                .lambda
-                .get(&tn)
-                .and_then(|trans| trans.get(ch))
-                .is_some();
-
-            if is_lambda_defined {
-                let mut word_with_ch = word.to_vec();
-                word_with_ch.push(*ch);
-
-                // NOTE: k = n at this point, idk
-                let output = self.iota + self.lambda_star(&word_with_ch) - self.lambda_i(k, output);
-
-                postponed_lambda_updates.push((*tn, *ch, output));
-            }
+        for t in &self.state(tn).transitions {
+            let mut word_with_ch = word.to_vec();
+            word_with_ch.push(t.ch);
+
+            // NOTE: k = n at this point, idk
+            let output = difference(self.prefix_output(&word_with_ch), self.lambda_i(k, output));
+
+            postponed_lambda_updates.push((tn, t.ch, output));
         }
 
         for (q, a, o) in postponed_lambda_updates {
-            add_to_or_insert(&mut self.lambda, q, a, o);
+            self.set_output(q, a, o);
         }
 
         if n - k == 0 {
-            let tn_output = max(output, self.output(&word)) - self.output(&word);
-            word_states
-                .last()
-                .and_then(|tm| self.psi.insert(*tm, tn_output));
+            let tn_output = difference(max(output, self.output(&word)), self.output(&word));
+            self.state_mut(tn).final_output = Some(tn_output);
         }
 
         // Update iota last, as lambda and psi use the old value
@@ -162,101 +185,383 @@ impl Transducer {
     }
 
     /** Adds a new entry to the transducer,
-     * that is NOT lexicographically greater than the last added entry*/
-    pub fn add_entry_out_of_order(&mut self, word: &str, output: usize) {
-        let word_vec = word.chars().collect();
+     * that is NOT lexicographically greater than the last added entry.
+     * In rank mode the output is ignored and the word gets its rank instead.
+     * Panics if an output would overflow, see `try_add_entry_out_of_order` */
+    pub fn add_entry_out_of_order(&mut self, word: &str, output: i64) {
+        if let Err(e) = self.try_add_entry_out_of_order(word, output) {
+            panic!("{}", e);
+        }
+    }
+
+    /** Like add_entry_out_of_order, but rejects an entry which would make an output overflow,
+     * leaving the transducer unchanged */
+    pub fn try_add_entry_out_of_order(
+        &mut self,
+        word: &str,
+        output: i64,
+    ) -> Result<(), OverflowError> {
+        if word.is_empty() {
+            panic!("The transducer cannot take epsilon as input!");
+        }
+
+        if self.ranked {
+            self.add_ranked_entry(word);
+            return Ok(());
+        }
+
+        self.check_edit(word, Some(output))?;
+
+        if self.is_empty() {
+            self.reset(Transducer::from_entry(word, output));
+            self.reduce_to_epsilon();
+            return Ok(());
+        }
+
+        let word_vec: Vec<char> = word.chars().collect();
         let word_lcp = self.longest_common_prefix(&word_vec);
 
         self.increase_except_from_epsilon_to_word(&word_lcp);
-        self.add_entry_in_order(word, output);
+
+        let is_existing_entry = word_lcp.len() == word_vec.len()
+            && self
+                .state_sequence(&word_lcp)
+                .last()
+                .is_some_and(|q| self.is_final(*q));
+
+        // An existing entry only gets its output replaced
+        if is_existing_entry {
+            self.replace_min_except_output(output);
+        } else {
+            self.add_entry_in_order(word, output);
+        }
         self.reduce_to_epsilon();
+
+        self.compact_if_fragmented();
+
+        #[cfg(feature = "check-invariants")]
+        self.assert_invariants();
+
+        return Ok(());
     }
 
-    /** Removes the entry with the given word from the transducer */
-    pub fn remove_entry_with_word(&mut self, word_raw: &str) {
-        if word_raw.is_empty() {
+    /** Adds entries in any order, replacing the outputs of existing words. Later entries
+     * replace earlier ones with the same word. In rank mode only the words are added.
+     * The batch is sorted, so that between two consecutive entries the transducer is only
+     * reduced to their common prefix, instead of to epsilon like in add_entry_out_of_order,
+     * and each word only has its output pushed along its path.
+     * Panics if an output would overflow, see `try_insert_batch` */
+    pub fn insert_batch<I, K>(&mut self, entries: I)
+    where
+        I: IntoIterator<Item = (K, i64)>,
+        K: AsRef<str>,
+    {
+        if let Err(e) = self.try_insert_batch(entries) {
+            panic!("{}", e);
+        }
+    }
+
+    /** Like insert_batch, but rejects the whole batch if one of its entries would make an
+     * output overflow. The entries before it are then undone, so the transducer keeps its
+     * entries like in try_add_entry_out_of_order */
+    pub fn try_insert_batch<I, K>(&mut self, entries: I) -> Result<(), OverflowError>
+    where
+        I: IntoIterator<Item = (K, i64)>,
+        K: AsRef<str>,
+    {
+        let batch: BTreeMap<String, i64> = entries
+            .into_iter()
+            .map(|(w, o)| (w.as_ref().to_string(), o))
+            .collect();
+
+        // Every word shifts the ranks of the following ones, so they are added one at a time
+        if self.ranked {
+            for word in batch.keys() {
+                self.add_ranked_entry(word);
+            }
+            return Ok(());
+        }
+
+        if batch.contains_key("") {
             panic!("The transducer cannot take epsilon as input!");
         }
 
-        let word = word_raw.chars().collect();
-        self.increase_except_from_epsilon_to_word(&word);
+        // The inserted words with their previous outputs, to undo them
+        let mut previous: Vec<(String, Option<i64>)> = Vec::new();
+
+        for (word, output) in batch {
+            if let Err(e) = self.check_edit(&word, Some(output)) {
+                self.reduce_to_epsilon();
+
+                // Like in try_remove_batch, undoing the entries in reverse order only passes
+                // through transducers that the batch has already passed through
+                for (word, output) in previous.into_iter().rev() {
+                    match output {
+                        Some(output) => self.add_entry_out_of_order(&word, output),
+                        None => {
+                            self.remove_entry_with_word(&word);
+                        }
+                    }
+                }
+                return Err(e);
+            }
+            previous.push((word.clone(), self.get(&word)));
 
-        let mut t_w = self.state_sequence(&word);
-        t_w.reverse();
+            if self.is_empty() {
+                self.reset(Transducer::from_entry(&word, output));
+                continue;
+            }
 
-        // Delete only if the current word has no continuation
-        if self.delta.get(&t_w[0]).is_none() {
-            // TODO: This won't work if the word, that is being deleted,
-            // is the last one in the dictionary
-            // FIX: just check if that is the case and cover it
-            let (_, prev_div_state) = self
-                .find_prev_divergent_state(&t_w[0])
-                .expect("Shouldn't be removing an entry with epsilon!");
+            let word_vec: Vec<char> = word.chars().collect();
+            let k = longest_common_prefix(&self.min_except, &word_vec).len();
+            self.reduce_except_by_k(self.min_except.len() - k);
 
-            for i in 0..t_w.len() {
-                if t_w[i] != prev_div_state {
-                    self.delete_state(&t_w[i]);
-                    self.min_except.pop();
-                } else {
-                    break;
+            let word_lcp = self.longest_common_prefix(&word_vec);
+            self.increase_except_to_word(&word_lcp);
+
+            // New and existing words alike only get their output set on the path, which avoids
+            // recomputing the outputs of every prefix like add_entry_in_order does
+            self.extend_min_except(&word_vec);
+            self.replace_min_except_output(output);
+        }
+        self.reduce_to_epsilon();
+
+        self.compact_if_fragmented();
+
+        #[cfg(feature = "check-invariants")]
+        self.assert_invariants();
+
+        return Ok(());
+    }
+
+    /** Removes the entries with the given words in any order and returns the removed
+     * entries in lexicographic order. Words without an entry are skipped. Like insert_batch,
+     * the words are sorted and the transducer is only reduced to the common prefix
+     * of consecutive words. Panics if an output would overflow, see `try_remove_batch` */
+    pub fn remove_batch<I, K>(&mut self, words: I) -> Vec<(String, i64)>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        match self.try_remove_batch(words) {
+            Ok(removed) => return removed,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /** Like remove_batch, but rejects the whole batch if removing one of its words would make
+     * an output overflow. The words removed before it are then added back, so the transducer
+     * keeps all of its entries */
+    pub fn try_remove_batch<I, K>(&mut self, words: I) -> Result<Vec<(String, i64)>, OverflowError>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        let batch: BTreeSet<String> = words.into_iter().map(|w| w.as_ref().to_string()).collect();
+        let mut removed = Vec::new();
+
+        // Removing the last word first keeps the ranks of the earlier ones
+        if self.ranked {
+            for word in batch.iter().rev() {
+                if let Some(rank) = self.remove_entry_with_word(word) {
+                    removed.push((word.clone(), rank));
                 }
             }
+            removed.reverse();
+            return Ok(removed);
         }
 
-        self.finality.remove(&t_w[0]);
-        self.psi.remove(&t_w[0]);
+        for word_raw in batch {
+            if word_raw.is_empty() {
+                panic!("The transducer cannot take epsilon as input!");
+            }
+
+            let Some(output) = self.get(&word_raw) else {
+                continue;
+            };
 
-        self.canonicalise_min_except();
+            if let Err(e) = self.check_edit(&word_raw, None) {
+                self.reduce_to_epsilon();
+
+                // In reverse order every word takes the transducer back to one that the batch
+                // has already passed through, so adding it cannot overflow either
+                for (word, output) in removed.into_iter().rev() {
+                    self.add_entry_out_of_order(&word, output);
+                }
+                return Err(e);
+            }
+
+            let word: Vec<char> = word_raw.chars().collect();
+            let k = longest_common_prefix(&self.min_except, &word).len();
+            self.reduce_except_by_k(self.min_except.len() - k);
+            self.increase_except_to_word(&word);
+
+            self.remove_min_except_word();
+            removed.push((word_raw, output));
+        }
         self.reduce_to_epsilon();
+
+        self.compact_if_fragmented();
+
+        #[cfg(feature = "check-invariants")]
+        self.assert_invariants();
+
+        return Ok(removed);
+    }
+
+    /** Removes the entry with the given word from the transducer and returns its output,
+     * or None if the transducer has no such entry. In rank mode the ranks of the following
+     * words are decremented. Panics if an output would overflow, see
+     * `try_remove_entry_with_word` */
+    pub fn remove_entry_with_word(&mut self, word_raw: &str) -> Option<i64> {
+        match self.try_remove_entry_with_word(word_raw) {
+            Ok(output) => return output,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /** Like remove_entry_with_word, but rejects the removal if an output would overflow, which
+     * happens when the word is the minimum of states with larger differences between the
+     * remaining outputs. The transducer is then left unchanged */
+    pub fn try_remove_entry_with_word(
+        &mut self,
+        word_raw: &str,
+    ) -> Result<Option<i64>, OverflowError> {
+        if word_raw.is_empty() {
+            panic!("The transducer cannot take epsilon as input!");
+        }
+
+        let Some(output) = self.get(word_raw) else {
+            return Ok(None);
+        };
+
+        if !self.ranked {
+            self.check_edit(word_raw, None)?;
+        }
+
+        let word: Vec<char> = word_raw.chars().collect();
+        self.increase_except_from_epsilon_to_word(&word);
+
+        if self.ranked {
+            self.shift_ranks_after(&word, false);
+        }
+
+        if !self.remove_min_except_word() {
+            return Ok(Some(output));
+        }
+        self.reduce_to_epsilon();
+
+        self.compact_if_fragmented();
+
+        #[cfg(feature = "check-invariants")]
+        self.assert_invariants();
+
+        return Ok(Some(output));
     }
 
     /** Constructs a minimal subsequential transducer from a dictionary of entries */
-    pub fn from_dictionary(dictionary: Vec<(&str, usize)>) -> Self {
-        if dictionary.is_empty() {
-            panic!("Cannot construct empty transducer!");
+    pub fn from_dictionary(dictionary: Vec<(&str, i64)>) -> Self {
+        return Self::from_sorted_iter(dictionary);
+    }
+
+    /** Constructs a minimal subsequential transducer from lexicographically sorted entries.
+     * The entries are consumed one at a time, so they can be streamed from a reader.
+     * Empty input gives a transducer without entries. Panics on unsorted or duplicate input,
+     * see `try_from_sorted_iter` */
+    pub fn from_sorted_iter<I, K>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = (K, i64)>,
+        K: AsRef<str>,
+    {
+        match Self::try_from_sorted_iter(entries, DuplicatePolicy::Reject) {
+            Ok(transducer) => return transducer,
+            Err(BuildError::Empty) => return Transducer::empty(),
+            Err(e) => panic!("{}", e),
         }
+    }
 
-        let (w, o) = dictionary[0];
-        let mut transducer = Transducer::from_entry_with_capacity(w, o, dictionary.len() * 30);
-        let mut n = 1;
+    /** Like from_sorted_iter, but reports invalid input as an error and resolves
+     * duplicate words with the given policy */
+    pub fn try_from_sorted_iter<I, K>(
+        entries: I,
+        policy: DuplicatePolicy,
+    ) -> Result<Self, BuildError>
+    where
+        I: IntoIterator<Item = (K, i64)>,
+        K: AsRef<str>,
+    {
+        let entries = entries.into_iter();
+        let mut builder = Builder::with_capacity(policy, entries.size_hint().0);
+
+        for (w, o) in entries {
+            builder.add(w.as_ref(), o)?;
+        }
+
+        return builder.finish();
+    }
+
+    /** Returns the output of a given word, or None if the word is not in the transducer */
+    pub fn get(&self, word: &str) -> Option<i64> {
+        let mut state = self.state(self.init_state);
+        let mut output = self.iota;
 
-        for e in &dictionary[1..] {
-            let (w, o) = *e;
-            // println!("{:?} Adding {:?}", n, w);
-            transducer.add_entry_in_order(w, o);
-            n += 1;
+        for ch in word.chars() {
+            let t = state.transition(ch)?;
+            output = sum(output, t.output);
+            state = self.state(t.to);
         }
 
-        transducer.reduce_to_epsilon();
-        return transducer;
+        return state
+            .final_output
+            .map(|final_output| sum(output, final_output));
+    }
+
+    /** Returns an iterator over all entries, in lexicographic order of their words */
+    pub fn entries(&self) -> Entries<'_> {
+        return Entries::new(self);
+    }
+
+    /** Returns an iterator over the entries, whose words start with the given prefix */
+    pub fn entries_with_prefix(&self, prefix: &str) -> Entries<'_> {
+        return Entries::with_prefix(self, prefix);
+    }
+
+    /** Checks if the transducer has no entries, e.g. after its only entry has been removed */
+    pub fn is_empty(&self) -> bool {
+        // The transducer is trim, so only the initial state is left without entries
+        let init = self.state(self.init_state);
+        return init.transitions.is_empty() && !init.is_final();
     }
 
     /** Returns the output of a given word from the transducer */
-    pub fn output(&self, word: &Vec<char>) -> usize {
+    pub fn output(&self, word: &Vec<char>) -> i64 {
         let final_output = self
-            .state_sequence(&word)
+            .state_sequence(word)
             .last()
-            .and_then(|q| self.psi.get(q))
-            .unwrap_or(&0);
-        return self.iota + self.lambda_star(&word) + final_output;
+            .and_then(|q| self.state(*q).final_output)
+            .unwrap_or(0);
+        return sum(self.prefix_output(word), final_output);
     }
 
     pub fn get_number_of_transitions(&self) -> usize {
         let mut n = 0;
-        for (_, trans) in &self.delta {
-            n += trans.len();
+        for (_, state) in self.live_states() {
+            n += state.transitions.len();
         }
         return n;
     }
 
     pub fn print(&self) {
-        println!("Number of states: {:?}", self.get_states().len());
+        println!("Number of states: {:?}", self.get_number_of_states());
         println!(
             "Number of transitions: {:?}",
             self.get_number_of_transitions()
         );
         println!("Initial output: {:?}", self.get_initial_output());
-        println!("Number of final states: {:?}", self.get_finality().len());
+        println!(
+            "Number of final states: {:?}",
+            self.get_number_of_final_states()
+        );
     }
 
     // ////////////////
@@ -264,76 +569,51 @@ impl Transducer {
     // ///////////////////
     //
     /** Constructs the trivial minimal subsequential transducer from a single entry */
-    fn from_entry(word: &str, output: usize) -> Self {
-        let word: Vec<char> = word.chars().collect();
-        let n = word.len();
-
-        let mut alphabet = HashSet::new();
-        let mut delta = HashMap::new();
-        let mut delta_inv = HashMap::new();
-        let mut lambda = HashMap::new();
-
-        for i in 0..n {
-            alphabet.insert(word[i]);
-
-            let state_transition = HashMap::from([(word[i], i + 1)]);
-            delta.insert(i, state_transition);
-
-            delta_inv.insert(i + 1, HashSet::from([(word[i], i)]));
-
-            let state_output = HashMap::from([(word[i], 0)]);
-            lambda.insert(i, state_output);
-        }
+    fn from_entry(word: &str, output: i64) -> Self {
+        return Self::from_entry_with_capacity(word, output, 0);
+    }
 
-        return Self {
-            alphabet,
-            states: (0..=n).collect(),
-            finality: BTreeSet::from([n]),
-            init_state: 0,
-            delta,
-            delta_inv,
-            lambda,
-            iota: output,
-            psi: HashMap::from([(n, 0)]),
-            min_except: word,
-            states_by_signature: HashMap::new(),
-        };
+    /** Constructs the transducer without any entries */
+    fn empty() -> Self {
+        return Transducer::from_parts(1, Vec::new(), Vec::new(), 0);
     }
 
-    /** Like from_entry but initializes some HashMaps with a given capacity */
-    fn from_entry_with_capacity(word: &str, output: usize, capacity: usize) -> Self {
+    /** Like from_entry but reserves space for a given number of states */
+    fn from_entry_with_capacity(word: &str, output: i64, capacity: usize) -> Self {
         let word: Vec<char> = word.chars().collect();
         let n = word.len();
 
         let mut alphabet = HashSet::new();
-        let mut delta = HashMap::with_capacity(capacity);
-        let mut delta_inv = HashMap::with_capacity(capacity);
-        let mut lambda = HashMap::with_capacity(capacity);
+        let mut states = Vec::with_capacity(max(capacity, n + 1));
+        states.resize_with(n + 1, || Some(State::default()));
 
         for i in 0..n {
             alphabet.insert(word[i]);
 
-            let state_transition = HashMap::from([(word[i], i + 1)]);
-            delta.insert(i, state_transition);
-
-            delta_inv.insert(i + 1, HashSet::from([(word[i], i)]));
-
-            let state_output = HashMap::from([(word[i], 0)]);
-            lambda.insert(i, state_output);
+            let transition = Transition {
+                ch: word[i],
+                to: i + 1,
+                output: 0,
+            };
+            states[i].as_mut().unwrap().transitions.push(transition);
+            states[i + 1]
+                .as_mut()
+                .unwrap()
+                .predecessors
+                .push((word[i], i));
         }
+        states[n].as_mut().unwrap().final_output = Some(0);
 
         return Self {
             alphabet,
-            states: (0..=n).collect(),
-            finality: BTreeSet::from([n]),
+            states,
+            free: Vec::new(),
             init_state: 0,
-            delta,
-            delta_inv,
-            lambda,
             iota: output,
-            psi: HashMap::from([(n, 0)]),
             min_except: word,
-            states_by_signature: HashMap::with_capacity(capacity),
+            register: Register::with_capacity(capacity),
+            compaction_threshold: None,
+            ranked: false,
         };
     }
 
@@ -344,15 +624,14 @@ impl Transducer {
         }
 
         let word = &self.min_except;
-        let t_w = self.state_sequence(&word);
+        let t_w = self.state_sequence(word);
         let n = word.len();
         let an = word[n - 1];
 
         if let Some(q) = self.state_eq(t_w[n]) {
-            let prev_output = self.lambda[&t_w[n - 1]][&an];
+            let prev_output = self.transition_output(t_w[n - 1], an);
             self.delete_state(&t_w[n]);
-            self.add_delta_transition(t_w[n - 1], an, q);
-            add_to_or_insert(&mut self.lambda, t_w[n - 1], an, prev_output);
+            self.add_delta_transition(t_w[n - 1], an, q, prev_output);
         } else {
             self.add_signature(t_w[n]);
         }
@@ -376,45 +655,45 @@ impl Transducer {
     }
 
     /** Makes a minimal subsequential transducer minimal except in a given word */
-    fn increase_except_from_epsilon_to_word(&mut self, word: &Vec<char>) {
+    fn increase_except_from_epsilon_to_word(&mut self, word: &[char]) {
         if !self.min_except.is_empty() {
             panic!("transduser must be minimal except in epsilon!");
         }
 
-        let mut current_state = self.init_state;
-        let mut max_state = *self.states.last().expect("States cannot be empty!");
-
-        self.remove_signature(current_state);
+        self.increase_except_to_word(word);
+    }
 
-        for i in 0..word.len() {
-            let next_state = self.delta[&current_state][&word[i]];
+    /** Makes a transducer, that is minimal except in a prefix of a given word,
+     * minimal except in the word */
+    fn increase_except_to_word(&mut self, word: &[char]) {
+        if !word.starts_with(&self.min_except) {
+            panic!("Transducer must be minimal except in a prefix of the word!");
+        }
 
-            if self.is_state_convergent(next_state) {
-                max_state += 1;
-                let new_state = max_state;
+        let k = self.min_except.len();
+        let mut current_state = *self.state_sequence(&self.min_except).last().unwrap();
 
-                self.states.insert(new_state);
+        if k == 0 {
+            self.remove_signature(current_state);
+        }
 
-                self.add_delta_transition(current_state, word[i], new_state);
+        for i in k..word.len() {
+            let next_state = self.state(current_state).next(word[i]).unwrap();
 
-                if let Some(trans) = self.delta_inv.get_mut(&next_state) {
-                    trans.remove(&(word[i], current_state));
-                }
+            if self.is_state_convergent(next_state) {
+                let new_state = self.new_state();
 
-                if self.finality.contains(&next_state) {
-                    self.finality.insert(new_state);
-                    self.psi.insert(new_state, self.psi[&next_state]);
-                }
+                // Redirecting the transition also removes it from the predecessors of next_state
+                let output = self.transition_output(current_state, word[i]);
+                self.add_delta_transition(current_state, word[i], new_state, output);
 
-                // Clone the transitions of the convergent successor
-                if let Some(trans) = self.delta.get(&next_state).cloned() {
-                    for (ch, q) in trans {
-                        self.add_delta_transition(new_state, ch, q);
-                    }
-                }
-                if let Some(output_trans) = self.lambda.get(&next_state).cloned() {
-                    self.lambda.insert(new_state, output_trans);
+                // Clone the final output and the transitions of the convergent successor
+                let next = self.state(next_state);
+                let final_output = next.final_output;
+                for t in next.transitions.clone() {
+                    self.add_delta_transition(new_state, t.ch, t.to, t.output);
                 }
+                self.state_mut(new_state).final_output = final_output;
 
                 current_state = new_state;
             } else {
@@ -428,76 +707,92 @@ impl Transducer {
 
     /** Checks if a state is convergent, meaning it has more than one ingoing transitions */
     fn is_state_convergent(&self, state: usize) -> bool {
-        return self
-            .delta_inv
-            .get(&state)
-            .is_some_and(|trans| trans.len() > 1);
+        return self.state(state).predecessors.len() > 1;
     }
 
     /** Searches for an equivalent state of `state` outside of t_w */
     fn state_eq(&self, q: usize) -> Option<usize> {
-        let state_sig = self.signature(q);
+        let state = self.state(q);
+        let q_eq = self.register.find(state.signature_hash(), |q_eq| {
+            q_eq == q || self.state(q_eq).has_same_signature(state)
+        });
 
-        if let Some(q_eq) = self.states_by_signature.get(&state_sig) {
-            if *q_eq != q {
-                return Some(*q_eq);
-            } else {
-                println!("FYI - this state already has this signature");
-            }
+        match q_eq {
+            Some(q_eq) if q_eq != q => return Some(q_eq),
+            Some(_) => println!("FYI - this state already has this signature"),
+            None => {}
         }
 
         return None;
     }
-
     // NOTE: delta[(q,a)] will panic if delta is not defined
     /** Finds the state sequence, corresponding to a given word */
-    fn state_sequence(&self, w: &Vec<char>) -> Vec<usize> {
+    fn state_sequence(&self, w: &[char]) -> Vec<usize> {
         let mut next = self.init_state;
         let mut path = vec![next];
 
         for i in 0..w.len() {
-            next = self.delta[&next][&w[i]];
+            next = self
+                .state(next)
+                .next(w[i])
+                .expect("Delta must be defined for the word!");
             path.push(next);
         }
 
         return path;
     }
 
-    /** Adds a delta transition, overwriting existing transition from the given state with the
-     * given character. Updates delta_inv but does NOT update state signatures! */
-    fn add_delta_transition(&mut self, q1: usize, a: char, q2: usize) {
-        match self.delta.get_mut(&q1) {
-            Some(dq_1) => {
-                dq_1.insert(a, q2);
-            }
-            None => {
-                self.delta.insert(q1, HashMap::from([(a, q2)]));
-            }
-        }
+    /** Adds a transition with its output, overwriting existing transition from the given state
+     * with the given character. Updates the predecessors but does NOT update state signatures! */
+    fn add_delta_transition(&mut self, q1: usize, a: char, q2: usize, output: i64) {
+        let transition = Transition {
+            ch: a,
+            to: q2,
+            output,
+        };
 
-        match self.delta_inv.get_mut(&q2) {
-            Some(di_q2) => {
-                di_q2.insert((a, q1));
-            }
-            None => {
-                self.delta_inv.insert(q2, HashSet::from([(a, q1)]));
-            }
+        match self.state_mut(q1).set_transition(transition) {
+            Some(prev) if prev.to == q2 => return,
+            Some(prev) => self.state_mut(prev.to).remove_predecessor(a, q1),
+            None => {}
         }
+        self.state_mut(q2).predecessors.push((a, q1));
+    }
+
+    /** Sets the output of an existing transition */
+    fn set_output(&mut self, q: usize, a: char, output: i64) {
+        self.state_mut(q)
+            .transition_mut(a)
+            .expect("Lambda must be defined if delta is defined")
+            .output = output;
     }
 
-    fn lambda_i(&self, i: usize, beta: usize) -> usize {
+    fn transition_output(&self, q: usize, a: char) -> i64 {
+        return self
+            .state(q)
+            .transition(a)
+            .expect("Lambda must be defined if delta is defined")
+            .output;
+    }
+
+    fn lambda_i(&self, i: usize, beta: i64) -> i64 {
         let word_prefix_i = &self.min_except[..i].to_vec();
-        return min(self.iota + self.lambda_star(word_prefix_i), beta);
+        return min(self.prefix_output(word_prefix_i), beta);
     }
 
-    /** Returns the accumulated transition output for a given word  */
-    fn lambda_star(&self, word: &Vec<char>) -> usize {
-        let mut output = 0;
+    /** Returns iota plus the accumulated transition output for a given word. Starting from iota
+     * keeps every partial sum between the outputs of two words, so that it cannot overflow */
+    fn prefix_output(&self, word: &Vec<char>) -> i64 {
+        let mut output = self.iota;
         let mut state = self.init_state;
 
         for ch in word {
-            output += self.lambda[&state][ch];
-            state = self.delta[&state][ch];
+            let t = self
+                .state(state)
+                .transition(*ch)
+                .expect("Delta must be defined for the word!");
+            output = sum(output, t.output);
+            state = t.to;
         }
 
         return output;
@@ -509,71 +804,152 @@ impl Transducer {
             panic!("Cannot delete init state!");
         }
 
-        if let Some(preds) = self.delta_inv.remove(state) {
-            for (ch, q) in preds {
-                remove_from_or_delete(&mut self.lambda, &q, &ch);
-                remove_from_or_delete(&mut self.delta, &q, &ch);
-            }
+        let removed = self.states[*state].take().expect("State must be live!");
+
+        for (ch, q) in removed.predecessors {
+            self.state_mut(q).remove_transition(ch);
         }
 
-        if let Some(succs) = self.delta.remove(state) {
-            for (ch, q) in succs.iter() {
-                if let Some(trans) = self.delta_inv.get_mut(q) {
-                    trans.remove(&(*ch, *state));
-                }
-            }
+        for t in removed.transitions {
+            self.state_mut(t.to).remove_predecessor(t.ch, *state);
         }
 
-        self.lambda.remove(state);
-        self.states.remove(state);
-        self.finality.remove(state);
-        self.psi.remove(state);
+        self.free.push(*state);
     }
 
     fn canonicalise_min_except(&mut self) {
         let t_w = self.state_sequence(&self.min_except);
         let tn = *t_w.last().expect("State sequence cannot be empty!");
 
-        let mut carry = self.extract_min_from_state(&tn);
+        // The carry is the change of the minimal output of a state, which may not fit into an
+        // output before the minimum of its predecessor is extracted
+        let mut carry = self.extract_min_from_state(&tn, None);
         let mut prev_div_state = self.find_prev_divergent_state(&tn);
 
         // NOTE: this is a transition is from delta_inv
         while let Some((ch, q)) = prev_div_state {
-            self.lambda.entry(q).and_modify(|trans| {
-                trans.entry(ch).and_modify(|o| {
-                    *o += carry;
-                });
-            });
-
-            carry = self.extract_min_from_state(&q);
+            carry = self.extract_min_from_state(&q, Some((ch, carry)));
             prev_div_state = self.find_prev_divergent_state(&q);
         }
 
-        self.iota += carry;
+        self.iota = narrow(i128::from(self.iota) + carry);
     }
 
-    /** Decreases all outputs of a state with their minimum and returns the found minimum */
-    fn extract_min_from_state(&mut self, state: &usize) -> usize {
-        // TODO! This could be empty
-        let mut outputs: Vec<usize> = self.lambda.get(state).map_or(Vec::new(), |out_trans| {
-            out_trans.iter().map(|(_, v)| *v).collect()
-        });
+    /** Removes the word min_except from the transducer, which must accept it, and
+     * canonicalises the rest of its path. Returns false if it was the only entry, so that
+     * the transducer is now empty */
+    fn remove_min_except_word(&mut self) -> bool {
+        let mut t_w = self.state_sequence(&self.min_except);
+        t_w.reverse();
+
+        // Delete only if the current word has no continuation
+        if self.state(t_w[0]).transitions.is_empty() {
+            let Some((_, prev_div_state)) = self.find_prev_divergent_state(&t_w[0]) else {
+                // Nothing diverges from the path of the word, so it is the only entry
+                self.reset(Transducer::empty());
+                return false;
+            };
+
+            for i in 0..t_w.len() {
+                if t_w[i] != prev_div_state {
+                    self.delete_state(&t_w[i]);
+                    self.min_except.pop();
+                } else {
+                    break;
+                }
+            }
+        } else {
+            self.state_mut(t_w[0]).final_output = None;
+        }
+
+        self.canonicalise_min_except();
+        return true;
+    }
+
+    /** Replaces the output of min_except, which must be accepted by the transducer.
+     * Pushes iota and the outputs along min_except down to its last state, so that its final
+     * output is the whole output of the word, and then canonicalises the path again */
+    fn replace_min_except_output(&mut self, output: i64) {
+        let t_w = self.state_sequence(&self.min_except);
+        let tn = *t_w.last().expect("State sequence cannot be empty!");
 
-        if let Some(v) = self.psi.get(state) {
-            outputs.push(*v);
+        let mut carry = self.iota;
+        self.iota = 0;
+
+        for i in 0..self.min_except.len() {
+            self.add_to_state_outputs(&t_w[i], carry);
+
+            let ch = self.min_except[i];
+            let path_transition = self
+                .state_mut(t_w[i])
+                .transition_mut(ch)
+                .expect("Lambda must be defined if delta is defined");
+            carry = path_transition.output;
+            path_transition.output = 0;
         }
-        let min_output = *outputs.iter().min().unwrap_or(&0);
 
-        self.psi.entry(*state).and_modify(|o| {
-            *o -= min_output;
-        });
+        self.add_to_state_outputs(&tn, carry);
+        self.state_mut(tn).final_output = Some(output);
 
-        if let Some(trans) = self.lambda.get_mut(state) {
-            for ch in trans.clone().keys() {
-                trans.entry(*ch).and_modify(|o| {
-                    *o -= min_output;
-                });
-            }
+        self.canonicalise_min_except();
+    }
+
+    /** Adds states for the suffix of a word that min_except is a prefix of, so that the
+     * transducer reads the word. The new transitions have the output 0 and the last state
+     * is made final, so the output of the word still has to be set */
+    fn extend_min_except(&mut self, word: &[char]) {
+        let k = self.min_except.len();
+        let mut q = *self.state_sequence(&self.min_except).last().unwrap();
+
+        self.update_alphabet_with_word(&word[k..]);
+
+        for &ch in &word[k..] {
+            let next = self.new_state();
+            self.add_delta_transition(q, ch, next, 0);
+            self.min_except.push(ch);
+            q = next;
+        }
+        self.state_mut(q).final_output.get_or_insert(0);
+    }
+
+    /** Increases all outputs of a state (including the final one) with a given value */
+    fn add_to_state_outputs(&mut self, state: &usize, value: i64) {
+        let state = self.state_mut(*state);
+
+        if let Some(o) = state.final_output.as_mut() {
+            *o = sum(*o, value);
+        }
+
+        for t in state.transitions.iter_mut() {
+            t.output = sum(t.output, value);
+        }
+    }
+
+    /** Decreases all outputs of a state with their minimum and returns the found minimum.
+     * A carry is first added to the output of the transition with the given character */
+    fn extract_min_from_state(&mut self, state: &usize, carry: Option<(char, i128)>) -> i128 {
+        let state = self.state_mut(*state);
+
+        let output = |t: &Transition| match carry {
+            Some((ch, carry)) if ch == t.ch => return i128::from(t.output) + carry,
+            _ => return i128::from(t.output),
+        };
+
+        // TODO! This could be empty
+        let min_output = state
+            .transitions
+            .iter()
+            .map(output)
+            .chain(state.final_output.map(i128::from))
+            .min()
+            .unwrap_or(0);
+
+        if let Some(o) = state.final_output.as_mut() {
+            *o = narrow(i128::from(*o) - min_output);
+        }
+
+        for t in state.transitions.iter_mut() {
+            t.output = narrow(output(t) - min_output);
         }
 
         return min_output;
@@ -588,7 +964,7 @@ impl Transducer {
             return None;
         }
         // NOTE: assumes that the state has exactly one predecessor
-        let (mut curr_ch, mut curr_state) = self.delta_inv[state].iter().last().unwrap();
+        let (mut curr_ch, mut curr_state) = *self.state(*state).predecessors.last().unwrap();
 
         // NOTE: This shouldn't loop infinitely
         loop {
@@ -600,19 +976,19 @@ impl Transducer {
                 return None;
             }
 
-            (curr_ch, curr_state) = *self.delta_inv[&curr_state].iter().last().unwrap();
+            (curr_ch, curr_state) = *self.state(curr_state).predecessors.last().unwrap();
         }
     }
 
     /** Finds the longest prefix of the words that the transducer reads */
-    fn longest_common_prefix(&self, word: &Vec<char>) -> Vec<char> {
-        let mut state = 0;
+    fn longest_common_prefix(&self, word: &[char]) -> Vec<char> {
+        let mut state = self.init_state;
         let mut prefix = Vec::new();
 
         for i in 0..word.len() {
-            match self.delta.get(&state).and_then(|trans| trans.get(&word[i])) {
+            match self.state(state).next(word[i]) {
                 Some(q) => {
-                    state = *q;
+                    state = q;
                     prefix.push(word[i]);
                 }
                 None => {
@@ -626,15 +1002,43 @@ impl Transducer {
 
     /** Add k new states to the transducer */
     fn add_new_states(&mut self, k: usize) -> Vec<usize> {
-        let max_state = *self.states.last().unwrap_or(&0);
-        let mut new_states = Vec::new();
+        return (0..k).map(|_| self.new_state()).collect();
+    }
 
-        for i in 1..=k {
-            self.states.insert(max_state + i);
-            new_states.push(max_state + i);
+    /** Adds a state without transitions, reusing a released id if there is one */
+    fn new_state(&mut self) -> usize {
+        match self.free.pop() {
+            Some(q) => {
+                self.states[q] = Some(State::default());
+                return q;
+            }
+            None => {
+                self.states.push(Some(State::default()));
+                return self.states.len() - 1;
+            }
         }
+    }
+
+    /** Returns a live state. Panics if its id has been released */
+    fn state(&self, q: usize) -> &State {
+        return self.states[q].as_ref().expect("State must be live!");
+    }
 
-        return new_states;
+    fn state_mut(&mut self, q: usize) -> &mut State {
+        return self.states[q].as_mut().expect("State must be live!");
+    }
+
+    /** Iterates over the live states with their ids */
+    fn live_states(&self) -> impl Iterator<Item = (usize, &State)> {
+        return self
+            .states
+            .iter()
+            .enumerate()
+            .filter_map(|(q, state)| state.as_ref().map(|state| (q, state)));
+    }
+
+    fn is_final(&self, q: usize) -> bool {
+        return self.state(q).is_final();
     }
 
     /** Add all new characters of a word in the transducer's alphabet */
@@ -646,47 +1050,59 @@ impl Transducer {
 
     /** Checks if a state is final or has more than one outgoing transitions */
     fn is_state_divergent(&self, state: &usize) -> bool {
-        return self.delta.get(state).is_some_and(|trans| trans.len() > 1)
-            || self.finality.contains(state);
-    }
-
-    fn signature(&self, q: usize) -> (Option<usize>, BTreeSet<(char, usize, usize)>) {
-        let final_output = self.psi.get(&q).map(|o| *o);
-        let mut transitions = BTreeSet::new();
-
-        for ch in &self.alphabet {
-            if let Some(q_dest) = self.delta.get(&q).and_then(|q_trans| q_trans.get(&ch)) {
-                let q_out = self
-                    .lambda
-                    .get(&q)
-                    .and_then(|q_out_trans| q_out_trans.get(&ch))
-                    .expect("Lambda must be defined if delta is defined");
-                transitions.insert((*ch, *q_dest, *q_out));
-            }
-        }
-
-        return (final_output, transitions);
+        let state = self.state(*state);
+        return state.transitions.len() > 1 || state.is_final();
     }
 
+    /** Registers a state, replacing an equivalent registered state */
     fn add_signature(&mut self, q: usize) {
-        self.states_by_signature.insert(self.signature(q), q);
+        let states = &self.states;
+        let state = states[q].as_ref().expect("State must be live!");
+
+        self.register.insert(state.signature_hash(), q, |q_eq| {
+            states[q_eq]
+                .as_ref()
+                .is_some_and(|s| s.has_same_signature(state))
+        });
     }
 
+    /** Unregisters the state with the signature of a given state, before it gets changed */
     fn remove_signature(&mut self, q: usize) {
-        self.states_by_signature.remove(&self.signature(q));
+        let states = &self.states;
+        let state = states[q].as_ref().expect("State must be live!");
+
+        self.register.remove(state.signature_hash(), |q_eq| {
+            q_eq == q
+                || states[q_eq]
+                    .as_ref()
+                    .is_some_and(|s| s.has_same_signature(state))
+        });
     }
 
+    #[allow(dead_code)]
     fn print_debug(&self) {
         println!("T alphabet: {:?}", self.alphabet);
-        println!("T states: {:?}", self.states);
-        println!("T finality: {:?}", self.finality);
+        for (q, state) in self.live_states() {
+            println!("T state {}: {:?}", q, state);
+        }
+        println!("T free: {:?}", self.free);
         println!("T init_state: {:?}", self.init_state);
-        println!("T delta: {:?}", self.delta);
-        println!("T delta inversed: {:?}", self.delta_inv);
-        println!("T lambda: {:?}", self.lambda);
         println!("T iota: {:?}", self.iota);
-        println!("T psi: {:?}", self.psi);
         println!("T min_except: {:?}", self.min_except);
-        println!("T states by signature: {:?}", self.states_by_signature);
+        println!("T register: {:?}", self.register);
+    }
+}
+
+/** Collects lexicographically sorted entries, see `Transducer::from_sorted_iter` */
+impl<K: AsRef<str>> FromIterator<(K, i64)> for Transducer {
+    fn from_iter<I: IntoIterator<Item = (K, i64)>>(entries: I) -> Self {
+        return Self::from_sorted_iter(entries);
+    }
+}
+
+/** Adds entries in any order as one batch, see `Transducer::insert_batch` */
+impl<K: AsRef<str>> Extend<(K, i64)> for Transducer {
+    fn extend<I: IntoIterator<Item = (K, i64)>>(&mut self, entries: I) {
+        self.insert_batch(entries);
     }
 }
@@ -0,0 +1,242 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use super::Transducer;
+
+/** Decides what happens to consecutive entries with the same word during in-order construction */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    #[default]
+    Reject,
+    KeepFirst,
+    KeepLast,
+    Min,
+    Sum,
+}
+
+impl DuplicatePolicy {
+    /** Combines the output of an already seen entry with the output of its duplicate,
+     * or returns None if the sum overflows */
+    fn combine(&self, old: i64, new: i64) -> Option<i64> {
+        match self {
+            DuplicatePolicy::Reject | DuplicatePolicy::KeepFirst => Some(old),
+            DuplicatePolicy::KeepLast => Some(new),
+            DuplicatePolicy::Min => Some(old.min(new)),
+            DuplicatePolicy::Sum => old.checked_add(new),
+        }
+    }
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(DuplicatePolicy::Reject),
+            "keep-first" => Ok(DuplicatePolicy::KeepFirst),
+            "keep-last" => Ok(DuplicatePolicy::KeepLast),
+            "min" => Ok(DuplicatePolicy::Min),
+            "sum" => Ok(DuplicatePolicy::Sum),
+            _ => Err(format!("unknown duplicate policy {:?}", s)),
+        }
+    }
+}
+
+/** Invalid input for in-order construction. `line` is the 1-based position of the entry */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    Empty,
+    EmptyWord {
+        line: usize,
+    },
+    OutOfOrder {
+        line: usize,
+        word: String,
+        previous: String,
+    },
+    Duplicate {
+        line: usize,
+        word: String,
+    },
+    /** The entry would make an output overflow, see `OverflowError` */
+    Overflow {
+        line: usize,
+        word: String,
+    },
+}
+
+impl BuildError {
+    /** The position of the offending entry, or None if there are no entries */
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            BuildError::Empty => None,
+            BuildError::EmptyWord { line }
+            | BuildError::OutOfOrder { line, .. }
+            | BuildError::Duplicate { line, .. }
+            | BuildError::Overflow { line, .. } => Some(*line),
+        }
+    }
+
+    /** Replaces the position of the offending entry, e.g. with its line in a file */
+    pub fn at_line(self, line: usize) -> Self {
+        match self {
+            BuildError::Empty => BuildError::Empty,
+            BuildError::EmptyWord { .. } => BuildError::EmptyWord { line },
+            BuildError::OutOfOrder { word, previous, .. } => BuildError::OutOfOrder {
+                line,
+                word,
+                previous,
+            },
+            BuildError::Duplicate { word, .. } => BuildError::Duplicate { line, word },
+            BuildError::Overflow { word, .. } => BuildError::Overflow { line, word },
+        }
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::Empty => write!(f, "Cannot construct empty transducer!"),
+            BuildError::EmptyWord { line } => {
+                write!(
+                    f,
+                    "line {}: the transducer cannot take epsilon as input",
+                    line
+                )
+            }
+            BuildError::OutOfOrder {
+                line,
+                word,
+                previous,
+            } => write!(
+                f,
+                "line {}: {:?} is not lexicographically greater than {:?}",
+                line, word, previous
+            ),
+            BuildError::Duplicate { line, word } => {
+                write!(f, "line {}: duplicate word {:?}", line, word)
+            }
+            BuildError::Overflow { line, word } => {
+                write!(f, "line {}: the outputs overflow with {:?}", line, word)
+            }
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+/** Constructs a minimal subsequential transducer from entries given in lexicographic order,
+ * checking the order and resolving duplicates with a `DuplicatePolicy`.
+ * The last entry is held back until the next distinct word arrives, so that its duplicates
+ * can still be combined into it */
+pub struct Builder {
+    transducer: Option<Transducer>,
+    policy: DuplicatePolicy,
+    capacity: usize,
+    /** The held back entry with the line of its last duplicate */
+    pending: Option<(String, i64, usize)>,
+    line: usize,
+}
+
+impl Builder {
+    pub fn new(policy: DuplicatePolicy) -> Self {
+        return Self::with_capacity(policy, 0);
+    }
+
+    /** Like new but preallocates the transducer for roughly `entries` entries. The minimal
+     * transducer of a dictionary usually has fewer states than entries, so that many states
+     * and registered signatures are reserved */
+    pub fn with_capacity(policy: DuplicatePolicy, entries: usize) -> Self {
+        return Self {
+            transducer: None,
+            policy,
+            capacity: entries,
+            pending: None,
+            line: 0,
+        };
+    }
+
+    /** Adds the next entry, which must not be lexicographically smaller than the previous one */
+    pub fn add(&mut self, word: &str, output: i64) -> Result<(), BuildError> {
+        self.line += 1;
+
+        if word.is_empty() {
+            return Err(BuildError::EmptyWord { line: self.line });
+        }
+
+        if let Some((prev_word, prev_output, prev_line)) = self.pending.as_mut() {
+            if prev_word.as_str() == word {
+                if self.policy == DuplicatePolicy::Reject {
+                    return Err(BuildError::Duplicate {
+                        line: self.line,
+                        word: word.to_string(),
+                    });
+                }
+                *prev_output = self.policy.combine(*prev_output, output).ok_or_else(|| {
+                    BuildError::Overflow {
+                        line: self.line,
+                        word: word.to_string(),
+                    }
+                })?;
+                *prev_line = self.line;
+                return Ok(());
+            }
+
+            if prev_word.as_str() > word {
+                return Err(BuildError::OutOfOrder {
+                    line: self.line,
+                    word: word.to_string(),
+                    previous: prev_word.clone(),
+                });
+            }
+        }
+
+        // Reuse the buffer of the pending word for the new one
+        let mut next_word = match self.pending.take() {
+            Some((prev_word, prev_output, prev_line)) => {
+                self.flush(&prev_word, prev_output, prev_line)?;
+                prev_word
+            }
+            None => String::new(),
+        };
+        next_word.clear();
+        next_word.push_str(word);
+        self.pending = Some((next_word, output, self.line));
+
+        return Ok(());
+    }
+
+    /** Adds the last pending entry and makes the transducer minimal */
+    pub fn finish(mut self) -> Result<Transducer, BuildError> {
+        if let Some((word, output, line)) = self.pending.take() {
+            self.flush(&word, output, line)?;
+        }
+
+        let mut transducer = self.transducer.ok_or(BuildError::Empty)?;
+        transducer.reduce_to_epsilon();
+        return Ok(transducer);
+    }
+
+    /** Adds an entry to the transducer, after checking that its outputs do not overflow */
+    fn flush(&mut self, word: &str, output: i64, line: usize) -> Result<(), BuildError> {
+        match self.transducer.as_mut() {
+            Some(transducer) => {
+                transducer
+                    .check_edit(word, Some(output))
+                    .map_err(|_| BuildError::Overflow {
+                        line,
+                        word: word.to_string(),
+                    })?;
+                transducer.add_entry_in_order(word, output);
+            }
+            None => {
+                self.transducer = Some(Transducer::from_entry_with_capacity(
+                    word,
+                    output,
+                    self.capacity,
+                ));
+            }
+        }
+
+        return Ok(());
+    }
+}
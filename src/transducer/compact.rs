@@ -0,0 +1,94 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::Transducer;
+
+/** The transducer with states renumbered 0..n in BFS order from the initial state */
+pub(super) struct Numbered {
+    pub(super) num_states: usize,
+    pub(super) finals: Vec<(usize, i64)>,
+    pub(super) transitions: Vec<(usize, char, usize, i64)>,
+}
+
+impl Transducer {
+    /** Renumbers the reachable states densely in BFS order from the initial state, releasing
+     * the free list and the characters that no transition reads anymore. The register is
+     * rebuilt with the new ids, so the transducer stays minimal except in epsilon */
+    pub fn compact(&mut self) {
+        let numbered = self.numbered();
+        let compacted = Transducer::from_parts(
+            numbered.num_states,
+            numbered.transitions,
+            numbered.finals,
+            self.iota,
+        );
+        self.reset(compacted);
+    }
+
+    /** Compacts the states automatically after an edit, once the share of released ids
+     * exceeds the given threshold (between 0 and 1). None turns the automatic compaction off */
+    pub fn set_compaction_threshold(&mut self, threshold: Option<f64>) {
+        if threshold.is_some_and(|t| !(0.0..=1.0).contains(&t)) {
+            panic!("The compaction threshold must be between 0 and 1!");
+        }
+        self.compaction_threshold = threshold;
+    }
+
+    /** The number of ids released by deleted states, which are not compacted yet */
+    pub fn get_number_of_released_ids(&self) -> usize {
+        return self.free.len();
+    }
+
+    /** Compacts the states if the share of released ids exceeds the compaction threshold */
+    pub(super) fn compact_if_fragmented(&mut self) {
+        let Some(threshold) = self.compaction_threshold else {
+            return;
+        };
+
+        if self.free.len() as f64 > threshold * self.states.len() as f64 {
+            self.compact();
+        }
+    }
+
+    /** Replaces the transducer by another one, keeping the compaction threshold and the
+     * rank mode */
+    pub(super) fn reset(&mut self, transducer: Transducer) {
+        let (compaction_threshold, ranked) = (self.compaction_threshold, self.ranked);
+        *self = transducer;
+        self.compaction_threshold = compaction_threshold;
+        self.ranked = ranked;
+    }
+
+    /** Renumbers the reachable states in BFS order from the initial state, visiting the
+     * transitions of each state sorted by character */
+    pub(super) fn numbered(&self) -> Numbered {
+        let mut ids = HashMap::from([(self.init_state, 0)]);
+        let mut queue = VecDeque::from([self.init_state]);
+        let mut finals = Vec::new();
+        let mut transitions = Vec::new();
+
+        while let Some(q) = queue.pop_front() {
+            let id = ids[&q];
+
+            let state = self.state(q);
+
+            if let Some(o) = state.final_output {
+                finals.push((id, o));
+            }
+
+            for t in &state.transitions {
+                let n = ids.len();
+                let dest_id = *ids.entry(t.to).or_insert_with(|| {
+                    queue.push_back(t.to);
+                    n
+                });
+                transitions.push((id, t.ch, dest_id, t.output));
+            }
+        }
+
+        return Numbered {
+            num_states: ids.len(),
+            finals,
+            transitions,
+        };
+    }
+}
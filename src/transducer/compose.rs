@@ -0,0 +1,345 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    fmt,
+};
+
+use super::{overflow::sum, Transducer};
+
+/** A transducer from words to words, e.g. from surface forms to lemmas. It is built as a trie
+ * over the input words, whose outputs are pushed towards the initial state, so that the common
+ * prefix of the outputs is emitted as early as possible, and whose equivalent states are then
+ * merged. A word may have several outputs, which makes the transducer p-subsequential instead
+ * of subsequential */
+#[derive(Debug, Clone)]
+pub struct StringTransducer {
+    states: Vec<StringState>,
+    iota: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct StringState {
+    /** (char, state, output) sorted by char */
+    transitions: Vec<(char, usize, String)>,
+    /** Sorted and without duplicates */
+    final_outputs: Vec<String>,
+}
+
+/** The composition maps a word to several outputs, so it is not a function */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompositionError {
+    pub word: String,
+    /** The different outputs of the word, in increasing order */
+    pub outputs: Vec<i64>,
+}
+
+impl fmt::Display for CompositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "the composition is not subsequential: {:?} has the outputs {:?}",
+            self.word, self.outputs
+        );
+    }
+}
+
+impl Error for CompositionError {}
+
+impl StringTransducer {
+    /** Constructs the transducer of (word, output) pairs given in any order. Pairs with the same
+     * word add outputs to it. Panics on an empty word */
+    pub fn from_pairs<I, K, V>(pairs: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut transducer = StringTransducer {
+            states: vec![StringState::default()],
+            iota: String::new(),
+        };
+
+        for (word, output) in pairs {
+            if word.as_ref().is_empty() {
+                panic!("The transducer cannot take epsilon as input!");
+            }
+
+            let mut q = 0;
+            for ch in word.as_ref().chars() {
+                q = match transducer.states[q].next(ch) {
+                    Some(next) => next,
+                    None => transducer.add_state(q, ch),
+                };
+            }
+
+            let finals = &mut transducer.states[q].final_outputs;
+            if let Err(i) = finals.binary_search_by(|f| f.as_str().cmp(output.as_ref())) {
+                finals.insert(i, output.as_ref().to_string());
+            }
+        }
+
+        transducer.push_outputs();
+        transducer.merge_equivalent_states();
+        return transducer;
+    }
+
+    /** Returns the outputs of a word in increasing order, or no outputs if it is not in the
+     * transducer */
+    pub fn get(&self, word: &str) -> Vec<String> {
+        let mut q = 0;
+        let mut prefix = self.iota.clone();
+
+        for ch in word.chars() {
+            let Some(i) = self.states[q].position(ch) else {
+                return Vec::new();
+            };
+            let (_, next, output) = &self.states[q].transitions[i];
+            prefix.push_str(output);
+            q = *next;
+        }
+
+        return self.states[q]
+            .final_outputs
+            .iter()
+            .map(|f| prefix.clone() + f)
+            .collect();
+    }
+
+    pub fn get_number_of_states(&self) -> usize {
+        return self.states.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.states[0].transitions.is_empty();
+    }
+
+    /** Adds a state after a transition with an empty output from q */
+    fn add_state(&mut self, q: usize, ch: char) -> usize {
+        let next = self.states.len();
+        self.states.push(StringState::default());
+
+        let transitions = &mut self.states[q].transitions;
+        let i = transitions.partition_point(|(c, _, _)| *c < ch);
+        transitions.insert(i, (ch, next, String::new()));
+        return next;
+    }
+
+    /** Moves the longest common prefix of the outputs of every state onto its ingoing
+     * transition. The states of a trie are created after their parent, so visiting them in
+     * decreasing order visits the children first */
+    fn push_outputs(&mut self) {
+        let mut parents: Vec<Option<(usize, char)>> = vec![None; self.states.len()];
+        for (q, state) in self.states.iter().enumerate() {
+            for (ch, to, _) in &state.transitions {
+                parents[*to] = Some((q, *ch));
+            }
+        }
+
+        for q in (0..self.states.len()).rev() {
+            let state = &mut self.states[q];
+            let prefix = common_prefix(
+                state
+                    .final_outputs
+                    .iter()
+                    .chain(state.transitions.iter().map(|(_, _, output)| output)),
+            );
+            if prefix.is_empty() {
+                continue;
+            }
+
+            for output in state.final_outputs.iter_mut() {
+                output.drain(..prefix.len());
+            }
+            for (_, _, output) in state.transitions.iter_mut() {
+                output.drain(..prefix.len());
+            }
+
+            match parents[q] {
+                Some((parent, ch)) => {
+                    let i = self.states[parent].position(ch).unwrap();
+                    self.states[parent].transitions[i].2.push_str(&prefix);
+                }
+                None => self.iota.push_str(&prefix),
+            }
+        }
+    }
+
+    /** Merges the states of the trie with the same final outputs and transitions, which are
+     * equivalent once the outputs are pushed. Like in push_outputs, the children are visited
+     * first, so their transitions already point to the merged states. The initial state
+     * reaches all others, so it is never merged and keeps the id 0 */
+    fn merge_equivalent_states(&mut self) {
+        let n = self.states.len();
+        let mut merged_into: Vec<usize> = (0..n).collect();
+        let mut register: HashMap<StringState, usize> = HashMap::new();
+
+        for q in (0..n).rev() {
+            let mut state = std::mem::take(&mut self.states[q]);
+            for (_, to, _) in state.transitions.iter_mut() {
+                *to = merged_into[*to];
+            }
+            merged_into[q] = *register.entry(state).or_insert(q);
+        }
+
+        let mut kept: Vec<(usize, StringState)> =
+            register.into_iter().map(|(s, q)| (q, s)).collect();
+        kept.sort_by_key(|(q, _)| *q);
+
+        let mut ids = vec![0; n];
+        for (id, (q, _)) in kept.iter().enumerate() {
+            ids[*q] = id;
+        }
+
+        self.states = kept
+            .into_iter()
+            .map(|(_, mut state)| {
+                for (_, to, _) in state.transitions.iter_mut() {
+                    *to = ids[*to];
+                }
+                return state;
+            })
+            .collect();
+    }
+}
+
+impl StringState {
+    fn position(&self, ch: char) -> Option<usize> {
+        return self
+            .transitions
+            .binary_search_by(|(c, _, _)| c.cmp(&ch))
+            .ok();
+    }
+
+    fn next(&self, ch: char) -> Option<usize> {
+        return self.position(ch).map(|i| self.transitions[i].1);
+    }
+}
+
+impl Transducer {
+    /** Composes a word-to-word transducer with a transducer of its outputs, mapping every word of
+     * the first to the output of its output in the second. Words whose outputs are not in the
+     * second are dropped. Both transducers are walked in parallel, feeding the outputs of the
+     * first into the second, so the intermediate words are never built. Fails if a word gets
+     * different outputs, since the composition is then not a function */
+    pub fn compose(
+        first: &StringTransducer,
+        second: &Transducer,
+    ) -> Result<Transducer, CompositionError> {
+        if first.is_empty() || second.is_empty() {
+            return Ok(Transducer::empty());
+        }
+        let Some((init, output)) = second.read(second.init_state, &first.iota) else {
+            return Ok(Transducer::empty());
+        };
+        let iota = sum(second.iota, output);
+
+        let mut ids = HashMap::from([((0, init), 0)]);
+        let mut parents: Vec<Option<(usize, char, i64)>> = vec![None];
+        let mut queue = VecDeque::from([(0, init)]);
+        let mut transitions = Vec::new();
+        let mut finals = Vec::new();
+
+        while let Some((q1, q2)) = queue.pop_front() {
+            let id = ids[&(q1, q2)];
+            let state = &first.states[q1];
+
+            let mut outputs: Vec<i64> = state
+                .final_outputs
+                .iter()
+                .filter_map(|f| {
+                    let (q, o) = second.read(q2, f)?;
+                    return second
+                        .state(q)
+                        .final_output
+                        .map(|final_output| sum(o, final_output));
+                })
+                .collect();
+            outputs.sort();
+            outputs.dedup();
+
+            match outputs[..] {
+                [] => {}
+                [o] => finals.push((id, o)),
+                _ => {
+                    let (word, output) = path_to(&parents, id);
+                    return Err(CompositionError {
+                        word,
+                        outputs: outputs.iter().map(|o| sum(sum(iota, output), *o)).collect(),
+                    });
+                }
+            }
+
+            for (ch, to, output) in &state.transitions {
+                let Some((next_q2, o)) = second.read(q2, output) else {
+                    continue;
+                };
+
+                let n = ids.len();
+                let next_id = *ids.entry((*to, next_q2)).or_insert_with(|| {
+                    parents.push(Some((id, *ch, o)));
+                    queue.push_back((*to, next_q2));
+                    n
+                });
+                transitions.push((id, *ch, next_id, o));
+            }
+        }
+
+        // The pairs that reach no word of the second transducer are trimmed by the minimisation
+        let composition = Transducer::from_parts(ids.len(), transitions, finals, iota);
+        return Ok(composition.minimised());
+    }
+
+    /** Follows a word from a state, returning the reached state and the output on the way */
+    fn read(&self, q: usize, word: &str) -> Option<(usize, i64)> {
+        let mut q = q;
+        let mut output = 0;
+
+        for ch in word.chars() {
+            let transition = self.state(q).transition(ch)?;
+            output = sum(output, transition.output);
+            q = transition.to;
+        }
+
+        return Some((q, output));
+    }
+}
+
+/** Returns the longest common prefix of the strings, or an empty string if there are none */
+fn common_prefix<'a, I>(strings: I) -> String
+where
+    I: Iterator<Item = &'a String>,
+{
+    let mut prefix: Option<&str> = None;
+
+    for s in strings {
+        prefix = Some(match prefix {
+            None => s,
+            Some(p) => {
+                let len = p
+                    .char_indices()
+                    .zip(s.chars())
+                    .find(|((_, a), b)| a != b)
+                    .map_or(p.len().min(s.len()), |((i, _), _)| i);
+                &p[..len]
+            }
+        });
+    }
+
+    return prefix.unwrap_or("").to_string();
+}
+
+/** Returns the word leading to a state with the output on the way, by following the parents
+ * back to the initial state */
+fn path_to(parents: &[Option<(usize, char, i64)>], id: usize) -> (String, i64) {
+    let mut chars = Vec::new();
+    let mut output = 0;
+    let mut next = parents[id];
+
+    while let Some((parent, ch, o)) = next {
+        chars.push(ch);
+        output = sum(output, o);
+        next = parents[parent];
+    }
+
+    return (chars.into_iter().rev().collect(), output);
+}
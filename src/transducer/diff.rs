@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use super::{overflow::sum, Transducer};
+
+/** A difference between the entries of an old and a new transducer */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffEntry {
+    Added(String, i64),
+    Removed(String, i64),
+    Changed { word: String, old: i64, new: i64 },
+}
+
+impl DiffEntry {
+    pub fn word(&self) -> &str {
+        match self {
+            DiffEntry::Added(word, _)
+            | DiffEntry::Removed(word, _)
+            | DiffEntry::Changed { word, .. } => return word,
+        }
+    }
+}
+
+/** Iterates over the differences of two transducers in lexicographic order of their words.
+ * Both transducers are walked depth first in lockstep, skipping the pairs of equivalent states
+ * reached with the same output, whose words all have the same outputs */
+pub struct Diff<'a> {
+    old: &'a Transducer,
+    new: &'a Transducer,
+    word: Vec<char>,
+    stack: Vec<Frame>,
+    /** Whether pairs of (old, new) states are equivalent, filled in on demand */
+    pub(super) equivalent: HashMap<(usize, usize), bool>,
+}
+
+/** The states reached by the current word in both transducers (None for no state) with the
+ * outputs accumulated up to them, the characters of their transitions and the next one to visit */
+struct Frame {
+    old: Option<(usize, i64)>,
+    new: Option<(usize, i64)>,
+    chars: Vec<char>,
+    next: usize,
+}
+
+impl<'a> Diff<'a> {
+    pub(super) fn new(old: &'a Transducer, new: &'a Transducer) -> Self {
+        let root = |t: &Transducer| (!t.is_empty()).then_some((t.init_state, t.iota));
+        let mut diff = Self {
+            old,
+            new,
+            word: Vec::new(),
+            stack: Vec::new(),
+            equivalent: HashMap::new(),
+        };
+
+        // The transducers cannot take epsilon as input, so the initial states are not final
+        // and the root only has to be pushed
+        let (old_root, new_root) = (root(old), root(new));
+        if !diff.is_shared(old_root, new_root) {
+            diff.stack.push(diff.frame(old_root, new_root));
+        }
+        return diff;
+    }
+
+    fn frame(&self, old: Option<(usize, i64)>, new: Option<(usize, i64)>) -> Frame {
+        let mut chars: Vec<char> = old
+            .into_iter()
+            .flat_map(|(q, _)| self.old.state(q).transitions.iter().map(|t| t.ch))
+            .chain(
+                new.into_iter()
+                    .flat_map(|(q, _)| self.new.state(q).transitions.iter().map(|t| t.ch)),
+            )
+            .collect();
+        chars.sort();
+        chars.dedup();
+
+        return Frame {
+            old,
+            new,
+            chars,
+            next: 0,
+        };
+    }
+
+    /** Checks if all words through a pair of states have the same output in both transducers */
+    fn is_shared(&mut self, old: Option<(usize, i64)>, new: Option<(usize, i64)>) -> bool {
+        match (old, new) {
+            (Some((q_old, o_old)), Some((q_new, o_new))) => {
+                return o_old == o_new && self.is_equivalent(q_old, q_new);
+            }
+            _ => return false,
+        }
+    }
+
+    /** Both transducers are minimal, so their states are in canonical form and two states
+     * are equivalent if their final outputs and their transitions are equal, up to equivalent
+     * targets */
+    fn is_equivalent(&mut self, q_old: usize, q_new: usize) -> bool {
+        if let Some(is_equivalent) = self.equivalent.get(&(q_old, q_new)) {
+            return *is_equivalent;
+        }
+
+        let (state_old, state_new) = (self.old.state(q_old), self.new.state(q_new));
+        let is_equivalent = state_old.final_output == state_new.final_output
+            && state_old.transitions.len() == state_new.transitions.len()
+            && state_old
+                .transitions
+                .iter()
+                .zip(&state_new.transitions)
+                .all(|(t_old, t_new)| {
+                    t_old.ch == t_new.ch
+                        && t_old.output == t_new.output
+                        && self.is_equivalent(t_old.to, t_new.to)
+                });
+
+        self.equivalent.insert((q_old, q_new), is_equivalent);
+        return is_equivalent;
+    }
+}
+
+impl Iterator for Diff<'_> {
+    type Item = DiffEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let frame = self.stack.last_mut()?;
+
+            if frame.next == frame.chars.len() {
+                self.stack.pop();
+                self.word.pop();
+                continue;
+            }
+
+            let ch = frame.chars[frame.next];
+            frame.next += 1;
+
+            let step = |t: &Transducer, side: Option<(usize, i64)>| {
+                let (q, o) = side?;
+                let transition = t.state(q).transition(ch)?;
+                return Some((transition.to, sum(o, transition.output)));
+            };
+            let (old, new) = (step(self.old, frame.old), step(self.new, frame.new));
+
+            if self.is_shared(old, new) {
+                continue;
+            }
+
+            self.word.push(ch);
+            self.stack.push(self.frame(old, new));
+
+            let final_output = |t: &Transducer, side: Option<(usize, i64)>| {
+                let (q, o) = side?;
+                return t.state(q).final_output.map(|f| sum(o, f));
+            };
+            let word = || self.word.iter().collect();
+
+            match (final_output(self.old, old), final_output(self.new, new)) {
+                (Some(o), Some(n)) if o != n => {
+                    return Some(DiffEntry::Changed {
+                        word: word(),
+                        old: o,
+                        new: n,
+                    })
+                }
+                (Some(o), None) => return Some(DiffEntry::Removed(word(), o)),
+                (None, Some(n)) => return Some(DiffEntry::Added(word(), n)),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Transducer {
+    /** Returns an iterator over the added, removed and changed entries of the new transducer
+     * compared to the old one, in lexicographic order of their words */
+    pub fn diff<'a>(old: &'a Transducer, new: &'a Transducer) -> Diff<'a> {
+        return Diff::new(old, new);
+    }
+}
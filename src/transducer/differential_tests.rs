@@ -0,0 +1,573 @@
+#[cfg(test)]
+mod differential_tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use proptest::{prelude::*, sample::Index};
+
+    use crate::transducer::{
+        BuildError, ConflictPolicy, DiffEntry, DuplicatePolicy, KeyEncoding, OverflowError,
+        StringTransducer, Transducer,
+    };
+
+    /** A mutation applied both to the transducer and to the BTreeMap oracle */
+    #[derive(Debug, Clone)]
+    enum Operation {
+        Insert(String, i64),
+        Remove(String),
+        RemoveExisting(Index),
+        Compact,
+    }
+
+    // A small alphabet and short words make shared prefixes and suffixes likely
+    fn word() -> impl Strategy<Value = String> {
+        return "[abc]{1,5}";
+    }
+
+    fn dictionary() -> impl Strategy<Value = BTreeMap<String, i64>> {
+        return prop::collection::btree_map(word(), -50..50i64, 1..20);
+    }
+
+    fn operation() -> impl Strategy<Value = Operation> {
+        return prop_oneof![
+            2 => (word(), -50..50i64).prop_map(|(w, o)| Operation::Insert(w, o)),
+            1 => word().prop_map(Operation::Remove),
+            2 => any::<Index>().prop_map(Operation::RemoveExisting),
+            1 => Just(Operation::Compact),
+        ];
+    }
+
+    // Outputs near the limits stress the checks, since the differences between them overflow
+    fn extreme_output() -> impl Strategy<Value = i64> {
+        return prop_oneof![
+            i64::MIN..i64::MIN + 50,
+            -50..50i64,
+            i64::MAX - 50..=i64::MAX,
+        ];
+    }
+
+    fn extreme_operation() -> impl Strategy<Value = Operation> {
+        return prop_oneof![
+            3 => (word(), extreme_output()).prop_map(|(w, o)| Operation::Insert(w, o)),
+            1 => word().prop_map(Operation::Remove),
+            2 => any::<Index>().prop_map(Operation::RemoveExisting),
+        ];
+    }
+
+    fn conflict_policy() -> impl Strategy<Value = ConflictPolicy> {
+        return prop_oneof![
+            Just(ConflictPolicy::Left),
+            Just(ConflictPolicy::Right),
+            Just(ConflictPolicy::Min),
+            Just(ConflictPolicy::Max),
+            Just(ConflictPolicy::Sum),
+        ];
+    }
+
+    // Operands of combinations may also be empty
+    fn operand() -> impl Strategy<Value = BTreeMap<String, i64>> {
+        return prop::collection::btree_map(word(), -50..50i64, 0..20);
+    }
+
+    fn from_oracle(oracle: &BTreeMap<String, i64>) -> Transducer {
+        if oracle.is_empty() {
+            return Transducer::empty();
+        }
+        return Transducer::from_sorted_iter(oracle.iter().map(|(w, o)| (w, *o)));
+    }
+
+    fn assert_same_function(
+        transducer: &Transducer,
+        oracle: &BTreeMap<String, i64>,
+    ) -> Result<(), TestCaseError> {
+        prop_assert_eq!(transducer.validate(), Ok(()));
+        prop_assert!(transducer.is_minimal());
+        prop_assert_eq!(
+            transducer.get_number_of_states(),
+            transducer.minimised().get_number_of_states()
+        );
+
+        let entries: Vec<(String, i64)> = transducer.entries().collect();
+        let expected: Vec<(String, i64)> = oracle.iter().map(|(w, o)| (w.clone(), *o)).collect();
+        prop_assert_eq!(entries, expected);
+
+        for (w, o) in oracle {
+            prop_assert_eq!(transducer.get(w), Some(*o));
+        }
+
+        return Ok(());
+    }
+
+    /** The outputs of the transitions are differences between outputs, so they can only
+     * overflow if the difference between the largest and the smallest output does */
+    fn may_overflow(oracle: &BTreeMap<String, i64>) -> bool {
+        let (Some(min), Some(max)) = (oracle.values().min(), oracle.values().max()) else {
+            return false;
+        };
+        return max.checked_sub(*min).is_none();
+    }
+
+    proptest! {
+        #[test]
+        fn builds_the_same_function_in_order(oracle in dictionary()) {
+            let transducer = Transducer::from_sorted_iter(oracle.iter().map(|(w, o)| (w, *o)));
+            assert_same_function(&transducer, &oracle)?;
+        }
+
+        #[test]
+        fn builds_the_same_transducer_in_parallel(oracle in dictionary(), threads in 1..6usize) {
+            let entries: Vec<(&String, i64)> = oracle.iter().map(|(w, o)| (w, *o)).collect();
+            let sequential = Transducer::from_sorted_iter(entries.iter().copied());
+            let parallel = Transducer::from_sorted_slice_parallel(&entries, threads);
+
+            let (mut expected, mut saved) = (Vec::new(), Vec::new());
+            sequential.save(&mut expected).unwrap();
+            parallel.save(&mut saved).unwrap();
+
+            prop_assert_eq!(saved, expected);
+            assert_same_function(&parallel, &oracle)?;
+        }
+
+        #[test]
+        fn builds_the_same_function_out_of_order(entries in prop::collection::vec((word(), -50..50i64), 1..20)) {
+            let (first_word, first_output) = entries[0].clone();
+            let mut transducer = Transducer::from_dictionary(vec![(&first_word, first_output)]);
+            let mut oracle = BTreeMap::from([(first_word, first_output)]);
+
+            for (w, o) in &entries[1..] {
+                transducer.add_entry_out_of_order(w, *o);
+                oracle.insert(w.clone(), *o);
+            }
+
+            assert_same_function(&transducer, &oracle)?;
+        }
+
+        #[test]
+        fn inserts_a_batch_into_the_same_function(
+            mut oracle in dictionary(),
+            batch in prop::collection::vec((word(), -50..50i64), 0..30),
+        ) {
+            let mut transducer = Transducer::from_sorted_iter(oracle.iter().map(|(w, o)| (w, *o)));
+
+            transducer.insert_batch(batch.iter().map(|(w, o)| (w, *o)));
+            oracle.extend(batch);
+
+            assert_same_function(&transducer, &oracle)?;
+        }
+
+        #[test]
+        fn removes_a_batch_from_the_same_function(
+            mut oracle in dictionary(),
+            batch in prop::collection::vec(word(), 0..30),
+        ) {
+            let mut transducer = Transducer::from_sorted_iter(oracle.iter().map(|(w, o)| (w, *o)));
+
+            let removed = transducer.remove_batch(&batch);
+            let mut expected = Vec::new();
+            for w in batch.iter().collect::<BTreeSet<_>>() {
+                if let Some(o) = oracle.remove(w) {
+                    expected.push((w.clone(), o));
+                }
+            }
+
+            prop_assert_eq!(removed, expected);
+            assert_same_function(&transducer, &oracle)?;
+        }
+
+        #[test]
+        fn unites_into_the_same_function(
+            left in operand(),
+            right in operand(),
+            policy in conflict_policy(),
+        ) {
+            let union = Transducer::union(&from_oracle(&left), &from_oracle(&right), policy);
+
+            let mut oracle = BTreeMap::new();
+            for (w, o) in left.iter().chain(right.iter()) {
+                let output = match (left.get(w), right.get(w)) {
+                    (Some(l), Some(r)) => match policy {
+                        ConflictPolicy::Left => *l,
+                        ConflictPolicy::Right => *r,
+                        ConflictPolicy::Min => *l.min(r),
+                        ConflictPolicy::Max => *l.max(r),
+                        ConflictPolicy::Sum => l + r,
+                    },
+                    _ => *o,
+                };
+                oracle.insert(w.clone(), output);
+            }
+            assert_same_function(&union, &oracle)?;
+        }
+
+        #[test]
+        fn intersects_into_the_same_function(left in operand(), right in operand()) {
+            let combine = |l: i64, r: i64| 2 * l + r;
+            let intersection =
+                Transducer::intersection(&from_oracle(&left), &from_oracle(&right), combine);
+
+            let oracle: BTreeMap<String, i64> = left
+                .iter()
+                .filter_map(|(w, l)| Some((w.clone(), combine(*l, *right.get(w)?))))
+                .collect();
+
+            assert_same_function(&intersection, &oracle)?;
+        }
+
+        #[test]
+        fn subtracts_into_the_same_function(left in operand(), right in operand()) {
+            let difference = Transducer::difference(&from_oracle(&left), &from_oracle(&right));
+
+            let mut oracle = left.clone();
+            oracle.retain(|w, _| !right.contains_key(w));
+
+            assert_same_function(&difference, &oracle)?;
+        }
+
+        #[test]
+        fn finds_a_counterexample_iff_the_functions_differ(
+            left in operand(),
+            right in operand(),
+        ) {
+            let (t_left, t_right) = (from_oracle(&left), from_oracle(&right));
+
+            let shortest = left
+                .keys()
+                .chain(right.keys())
+                .filter(|w| left.get(*w) != right.get(*w))
+                .map(|w| w.chars().count())
+                .min();
+
+            match t_left.counterexample(&t_right) {
+                Some(w) => {
+                    prop_assert_ne!(left.get(&w), right.get(&w));
+                    prop_assert_eq!(Some(w.chars().count()), shortest);
+                }
+                None => prop_assert_eq!(&left, &right),
+            }
+            prop_assert_eq!(t_left == t_right, left == right);
+            prop_assert!(t_left == t_left.minimised());
+        }
+
+        #[test]
+        fn diffs_like_the_oracles(old in operand(), new in operand()) {
+            let diff: Vec<DiffEntry> =
+                Transducer::diff(&from_oracle(&old), &from_oracle(&new)).collect();
+
+            let words: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            let expected: Vec<DiffEntry> = words
+                .into_iter()
+                .filter_map(|w| match (old.get(w), new.get(w)) {
+                    (Some(o), Some(n)) if o != n => Some(DiffEntry::Changed {
+                        word: w.clone(),
+                        old: *o,
+                        new: *n,
+                    }),
+                    (Some(o), None) => Some(DiffEntry::Removed(w.clone(), *o)),
+                    (None, Some(n)) => Some(DiffEntry::Added(w.clone(), *n)),
+                    _ => None,
+                })
+                .collect();
+
+            prop_assert_eq!(diff, expected);
+        }
+
+        #[test]
+        fn composes_like_the_oracles(
+            pairs in prop::collection::vec((word(), "[abc]{0,4}"), 0..20),
+            ids in operand(),
+        ) {
+            let lemmas = StringTransducer::from_pairs(pairs.iter().map(|(w, l)| (w, l)));
+            for (w, _) in &pairs {
+                let expected: BTreeSet<&str> = pairs
+                    .iter()
+                    .filter(|(v, _)| v == w)
+                    .map(|(_, l)| l.as_str())
+                    .collect();
+                prop_assert_eq!(lemmas.get(w), expected.into_iter().collect::<Vec<_>>());
+            }
+
+            let mut oracle: BTreeMap<String, BTreeSet<i64>> = BTreeMap::new();
+            for (w, l) in &pairs {
+                if let Some(o) = ids.get(l) {
+                    oracle.entry(w.clone()).or_default().insert(*o);
+                }
+            }
+
+            match Transducer::compose(&lemmas, &from_oracle(&ids)) {
+                Ok(composition) => {
+                    prop_assert!(oracle.values().all(|outputs| outputs.len() == 1));
+                    let oracle = oracle
+                        .into_iter()
+                        .map(|(w, outputs)| (w, *outputs.first().unwrap()))
+                        .collect();
+                    assert_same_function(&composition, &oracle)?;
+                }
+                Err(e) => {
+                    prop_assert!(e.outputs.len() > 1);
+                    prop_assert_eq!(
+                        e.outputs,
+                        oracle[&e.word].iter().copied().collect::<Vec<_>>()
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn inverts_like_the_oracle(oracle in operand(), width in 1..3usize) {
+            let transducer = from_oracle(&oracle);
+            let is_injective =
+                oracle.values().collect::<BTreeSet<_>>().len() == oracle.len();
+
+            for encoding in [KeyEncoding::Decimal, KeyEncoding::FixedWidth(width)] {
+                let (Ok(inverse), Ok(words)) =
+                    (transducer.invert(encoding), transducer.invert_to_words(encoding))
+                else {
+                    prop_assert!(!is_injective);
+                    continue;
+                };
+                prop_assert!(is_injective);
+
+                let expected: BTreeMap<String, i64> = oracle
+                    .values()
+                    .enumerate()
+                    .map(|(rank, o)| (encoding.encode(*o).unwrap(), rank as i64))
+                    .collect();
+                assert_same_function(&inverse, &expected)?;
+
+                for (w, o) in &oracle {
+                    prop_assert_eq!(words.get(&encoding.encode(*o).unwrap()), vec![w.clone()]);
+                }
+            }
+        }
+
+        #[test]
+        fn keeps_the_same_function_after_mutations(
+            mut oracle in dictionary(),
+            operations in prop::collection::vec(operation(), 1..30),
+        ) {
+            let mut transducer = Transducer::from_sorted_iter(oracle.iter().map(|(w, o)| (w, *o)));
+
+            for operation in operations {
+                match operation {
+                    Operation::Insert(w, o) => {
+                        transducer.add_entry_out_of_order(&w, o);
+                        oracle.insert(w, o);
+                    }
+                    Operation::Remove(w) => {
+                        prop_assert_eq!(transducer.remove_entry_with_word(&w), oracle.remove(&w));
+                    }
+                    Operation::RemoveExisting(i) => {
+                        if oracle.is_empty() {
+                            continue;
+                        }
+                        let w = oracle.keys().nth(i.index(oracle.len())).unwrap().clone();
+                        prop_assert_eq!(transducer.remove_entry_with_word(&w), oracle.remove(&w));
+                    }
+                    Operation::Compact => {
+                        transducer.compact();
+                        prop_assert_eq!(transducer.get_number_of_released_ids(), 0);
+                    }
+                }
+
+                assert_same_function(&transducer, &oracle)?;
+            }
+        }
+
+        #[test]
+        fn keeps_the_ranks_after_mutations(
+            mut oracle in prop::collection::btree_set(word(), 1..20),
+            operations in prop::collection::vec(operation(), 1..30),
+        ) {
+            let mut transducer = Transducer::from_sorted_words(&oracle);
+            let ranks = |oracle: &BTreeSet<String>| -> BTreeMap<String, i64> {
+                return oracle.iter().enumerate().map(|(rank, w)| (w.clone(), rank as i64)).collect();
+            };
+
+            for operation in operations {
+                match operation {
+                    Operation::Insert(w, o) => {
+                        let rank = oracle.range::<String, _>(..&w).count() as i64;
+                        prop_assert_eq!(transducer.rank_of(&w), rank);
+                        transducer.add_entry_out_of_order(&w, o);
+                        oracle.insert(w);
+                    }
+                    Operation::Remove(w) => {
+                        prop_assert_eq!(transducer.remove_entry_with_word(&w), ranks(&oracle).get(&w).copied());
+                        oracle.remove(&w);
+                    }
+                    Operation::RemoveExisting(i) => {
+                        if oracle.is_empty() {
+                            continue;
+                        }
+                        let rank = i.index(oracle.len());
+                        let w = oracle.iter().nth(rank).unwrap().clone();
+                        prop_assert_eq!(transducer.remove_entry_with_word(&w), Some(rank as i64));
+                        oracle.remove(&w);
+                    }
+                    Operation::Compact => transducer.compact(),
+                }
+
+                prop_assert!(transducer.is_ranked());
+                assert_same_function(&transducer, &ranks(&oracle))?;
+            }
+        }
+
+        #[test]
+        fn keeps_the_ranks_after_batches(
+            oracle in prop::collection::btree_set(word(), 1..20),
+            inserted in prop::collection::vec(word(), 0..10),
+            removed in prop::collection::vec(word(), 0..10),
+        ) {
+            let mut transducer = Transducer::from_sorted_words(&oracle);
+
+            transducer.insert_batch(inserted.iter().map(|w| (w, 0)));
+            let mut expected: BTreeSet<String> = oracle.union(&inserted.into_iter().collect()).cloned().collect();
+            let ranks: Vec<(String, i64)> = expected.iter().cloned().enumerate().map(|(rank, w)| (w, rank as i64)).collect();
+            assert_same_function(&transducer, &ranks.iter().cloned().collect())?;
+
+            let removed: BTreeSet<String> = removed.into_iter().collect();
+            let expected_removed: Vec<(String, i64)> =
+                ranks.into_iter().filter(|(w, _)| removed.contains(w)).collect();
+            prop_assert_eq!(transducer.remove_batch(&removed), expected_removed);
+
+            expected.retain(|w| !removed.contains(w));
+            let ranks = expected.into_iter().enumerate().map(|(rank, w)| (w, rank as i64)).collect();
+            assert_same_function(&transducer, &ranks)?;
+        }
+
+        #[test]
+        fn builds_or_rejects_extreme_outputs(
+            oracle in prop::collection::btree_map(word(), extreme_output(), 1..20),
+            threads in 1..4usize,
+        ) {
+            let entries: Vec<(&String, i64)> = oracle.iter().map(|(w, o)| (w, *o)).collect();
+            let sequential = Transducer::try_from_sorted_iter(entries.iter().copied(), DuplicatePolicy::Reject);
+            let parallel = Transducer::try_from_sorted_slice_parallel(&entries, DuplicatePolicy::Reject, threads);
+
+            // The shards never hold the entries of other shards, so intermediate outputs of the
+            // sequential construction may overflow even though the ones of the shards do not
+            match (sequential, parallel) {
+                (Ok(sequential), Ok(parallel)) => {
+                    prop_assert!(sequential == parallel);
+                    assert_same_function(&sequential, &oracle)?;
+                }
+                (Err(sequential), parallel) => {
+                    prop_assert!(matches!(sequential, BuildError::Overflow { .. }), "{}", sequential);
+                    prop_assert!(may_overflow(&oracle));
+                    match parallel {
+                        Ok(parallel) => assert_same_function(&parallel, &oracle)?,
+                        Err(parallel) => prop_assert_eq!(sequential, parallel),
+                    }
+                }
+                (Ok(_), Err(e)) => prop_assert!(false, "only the parallel construction fails: {}", e),
+            }
+        }
+
+        #[test]
+        fn keeps_the_same_function_with_extreme_outputs(
+            mut oracle in dictionary(),
+            operations in prop::collection::vec(extreme_operation(), 1..30),
+        ) {
+            let mut transducer = Transducer::from_sorted_iter(oracle.iter().map(|(w, o)| (w, *o)));
+
+            for operation in operations {
+                let mut edited = oracle.clone();
+                let result = match operation {
+                    Operation::Insert(w, o) => {
+                        edited.insert(w.clone(), o);
+                        transducer.try_add_entry_out_of_order(&w, o)
+                    }
+                    Operation::Remove(w) => {
+                        let removed = edited.remove(&w);
+                        let result = transducer.try_remove_entry_with_word(&w);
+                        if let Ok(o) = result {
+                            prop_assert_eq!(o, removed);
+                        }
+                        result.map(|_| ())
+                    }
+                    Operation::RemoveExisting(i) => {
+                        if oracle.is_empty() {
+                            continue;
+                        }
+                        let w = oracle.keys().nth(i.index(oracle.len())).unwrap().clone();
+                        let removed = edited.remove(&w);
+                        let result = transducer.try_remove_entry_with_word(&w);
+                        if let Ok(o) = result {
+                            prop_assert_eq!(o, removed);
+                        }
+                        result.map(|_| ())
+                    }
+                    Operation::Compact => unreachable!("Compaction does not change the outputs"),
+                };
+
+                // A rejected edit leaves the transducer unchanged
+                match result {
+                    Ok(()) => oracle = edited,
+                    Err(_) => prop_assert!(may_overflow(&edited)),
+                }
+                assert_same_function(&transducer, &oracle)?;
+            }
+        }
+
+        #[test]
+        fn inserts_a_batch_with_extreme_outputs(
+            mut oracle in dictionary(),
+            batch in prop::collection::btree_map(word(), extreme_output(), 0..30),
+        ) {
+            let mut transducer = Transducer::from_sorted_iter(oracle.iter().map(|(w, o)| (w, *o)));
+
+            // A rejected batch leaves the transducer unchanged
+            let mut edited = oracle.clone();
+            match transducer.try_insert_batch(batch.iter().map(|(w, o)| (w, *o))) {
+                Ok(()) => {
+                    edited.extend(batch);
+                    oracle = edited;
+                }
+                Err(OverflowError { word, output }) => {
+                    prop_assert_eq!(Some(batch[&word]), output);
+                    edited.extend(batch.into_iter().filter(|(w, _)| *w <= word));
+                    prop_assert!(may_overflow(&edited));
+                }
+            }
+
+            assert_same_function(&transducer, &oracle)?;
+        }
+
+        #[test]
+        fn removes_a_batch_with_extreme_outputs(
+            entries in prop::collection::btree_map(word(), extreme_output(), 1..20),
+            other_words in prop::collection::btree_set(word(), 0..10),
+        ) {
+            let mut transducer = Transducer::empty();
+            let mut oracle = BTreeMap::new();
+            for (w, o) in entries {
+                if transducer.try_add_entry_out_of_order(&w, o).is_ok() {
+                    oracle.insert(w, o);
+                }
+            }
+
+            // Every other entry is removed, with words that may not be in the transducer
+            let batch: BTreeSet<String> =
+                oracle.keys().step_by(2).cloned().chain(other_words).collect();
+
+            // A rejected batch leaves the transducer unchanged
+            match transducer.try_remove_batch(&batch) {
+                Ok(removed) => {
+                    let expected: Vec<(String, i64)> = oracle
+                        .iter()
+                        .filter(|(w, _)| batch.contains(*w))
+                        .map(|(w, o)| (w.clone(), *o))
+                        .collect();
+                    prop_assert_eq!(removed, expected);
+                    oracle.retain(|w, _| !batch.contains(w));
+                }
+                Err(OverflowError { word, output }) => {
+                    prop_assert_eq!(output, None);
+                    let mut edited = oracle.clone();
+                    edited.retain(|w, _| !batch.contains(w) || *w > word);
+                    prop_assert!(may_overflow(&edited));
+                }
+            }
+            assert_same_function(&transducer, &oracle)?;
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt::Write;
+
+use super::Transducer;
+
+impl Transducer {
+    /** Renders the transducer in the Graphviz DOT language. Transitions are labeled with
+     * char/output, final states are double circles labeled with their final output and
+     * the initial arrow is labeled with iota */
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph transducer {\n    rankdir=LR;\n");
+        dot.push_str("    start [shape=point];\n");
+
+        for (q, state) in self.live_states() {
+            match state.final_output {
+                Some(o) => writeln!(
+                    dot,
+                    "    {} [shape=doublecircle, label=\"{}/{}\"];",
+                    q, q, o
+                ),
+                None => writeln!(dot, "    {} [shape=circle];", q),
+            }
+            .unwrap();
+        }
+
+        writeln!(
+            dot,
+            "    start -> {} [label=\"{}\"];",
+            self.init_state, self.iota
+        )
+        .unwrap();
+
+        for (q, state) in self.live_states() {
+            for t in &state.transitions {
+                writeln!(
+                    dot,
+                    "    {} -> {} [label=\"{}/{}\"];",
+                    q,
+                    t.to,
+                    t.ch.escape_default(),
+                    t.output
+                )
+                .unwrap();
+            }
+        }
+
+        dot.push_str("}\n");
+        return dot;
+    }
+}
@@ -0,0 +1,99 @@
+use super::{overflow::sum, Transducer};
+
+/** Iterates over the entries of a transducer in lexicographic order of their words,
+ * walking delta depth first with the transitions of each state sorted by character */
+pub struct Entries<'a> {
+    transducer: &'a Transducer,
+    word: Vec<char>,
+    stack: Vec<Frame>,
+    is_root_pending: bool,
+}
+
+/** A visited state on the current path, with the output accumulated up to it
+ * and the index of its next transition to visit */
+struct Frame {
+    state: usize,
+    next: usize,
+    output: i64,
+}
+
+impl Frame {
+    fn new(state: usize, output: i64) -> Self {
+        return Self {
+            state,
+            next: 0,
+            output,
+        };
+    }
+}
+
+impl<'a> Entries<'a> {
+    pub(super) fn new(transducer: &'a Transducer) -> Self {
+        return Self::with_prefix(transducer, "");
+    }
+
+    /** Iterates only over the entries, whose words start with the given prefix */
+    pub(super) fn with_prefix(transducer: &'a Transducer, prefix: &str) -> Self {
+        let mut state = transducer.init_state;
+        let mut output = transducer.iota;
+        let mut entries = Self {
+            transducer,
+            word: Vec::new(),
+            stack: Vec::new(),
+            is_root_pending: false,
+        };
+
+        for ch in prefix.chars() {
+            match transducer.state(state).transition(ch) {
+                Some(t) => {
+                    output = sum(output, t.output);
+                    state = t.to;
+                    entries.word.push(ch);
+                }
+                None => return entries,
+            }
+        }
+
+        entries.stack.push(Frame::new(state, output));
+        entries.is_root_pending = transducer.is_final(state);
+        return entries;
+    }
+}
+
+impl Iterator for Entries<'_> {
+    type Item = (String, i64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_root_pending {
+            self.is_root_pending = false;
+            let root = &self.stack[0];
+            let output = sum(
+                root.output,
+                self.transducer.state(root.state).final_output.unwrap(),
+            );
+            return Some((self.word.iter().collect(), output));
+        }
+
+        loop {
+            let frame = self.stack.last_mut()?;
+            let transitions = &self.transducer.state(frame.state).transitions;
+
+            if frame.next == transitions.len() {
+                self.stack.pop();
+                self.word.pop();
+                continue;
+            }
+
+            let t = transitions[frame.next];
+            let next_output = sum(frame.output, t.output);
+            frame.next += 1;
+
+            self.word.push(t.ch);
+            self.stack.push(Frame::new(t.to, next_output));
+
+            if let Some(final_output) = self.transducer.state(t.to).final_output {
+                return Some((self.word.iter().collect(), sum(next_output, final_output)));
+            }
+        }
+    }
+}
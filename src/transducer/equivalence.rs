@@ -0,0 +1,153 @@
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+
+use super::Transducer;
+
+/** A pair of states reached by the same word, with the outputs owed to the words through them
+ * after the output both transducers have in common. None stands for a missing state.
+ * The owed outputs are differences between the outputs of both transducers, which may not
+ * fit into an i64 */
+type Pair = (Option<(usize, i128)>, Option<(usize, i128)>);
+
+impl Transducer {
+    /** Checks if both transducers define the same function */
+    pub fn equivalent(&self, other: &Transducer) -> bool {
+        return self.counterexample(other).is_none();
+    }
+
+    /** Returns a shortest word whose output differs between the transducers, or that is in only
+     * one of them, or None if they define the same function. Both transducers are walked
+     * simultaneously in BFS order from their initial states, comparing their final outputs,
+     * transitions and outputs. Minimal transducers of the same function are walked in lockstep,
+     * independently of their state ids. A state that is missing on one side only gives a
+     * candidate, as the shortest word through it may be longer than a later counterexample */
+    pub fn counterexample(&self, other: &Transducer) -> Option<String> {
+        let root = |t: &Transducer| (!t.is_empty()).then_some((t.init_state, i128::from(t.iota)));
+        let init = normalise(root(self), root(other));
+
+        let mut parents: HashMap<Pair, Option<(Pair, char)>> = HashMap::from([(init, None)]);
+        let mut queue = VecDeque::from([(init, 0)]);
+        let mut candidate: Option<(usize, String)> = None;
+
+        while let Some((pair, depth)) = queue.pop_front() {
+            let (left, right) = pair;
+
+            // The words through the pair are at least as long as the candidate
+            if candidate.as_ref().is_some_and(|(len, _)| *len <= depth) {
+                break;
+            }
+
+            // A state that is missing on one side diverges with all words of the other side
+            let suffix = match (left, right) {
+                (Some((q, _)), None) => Some(self.shortest_word_from(q)),
+                (None, Some((q, _))) => Some(other.shortest_word_from(q)),
+                _ => None,
+            };
+            if let Some(suffix) = suffix {
+                let len = depth + suffix.chars().count();
+                if candidate.as_ref().is_none_or(|(best, _)| len < *best) {
+                    candidate = Some((len, path_to(&parents, pair) + &suffix));
+                }
+                continue;
+            }
+
+            let final_output = |t: &Transducer, side: Option<(usize, i128)>| {
+                let (q, o) = side?;
+                return t.state(q).final_output.map(|f| o + i128::from(f));
+            };
+            if final_output(self, left) != final_output(other, right) {
+                return Some(path_to(&parents, pair));
+            }
+
+            let mut next_chars: Vec<char> = left
+                .into_iter()
+                .flat_map(|(q, _)| self.state(q).transitions.iter().map(|t| t.ch))
+                .chain(
+                    right
+                        .into_iter()
+                        .flat_map(|(q, _)| other.state(q).transitions.iter().map(|t| t.ch)),
+                )
+                .collect();
+            next_chars.sort();
+            next_chars.dedup();
+
+            for ch in next_chars {
+                let step = |t: &Transducer, side: Option<(usize, i128)>| {
+                    let (q, o) = side?;
+                    let transition = t.state(q).transition(ch)?;
+                    return Some((transition.to, o + i128::from(transition.output)));
+                };
+                let next = normalise(step(self, left), step(other, right));
+
+                if let Entry::Vacant(entry) = parents.entry(next) {
+                    entry.insert(Some((pair, ch)));
+                    queue.push_back((next, depth + 1));
+                }
+            }
+        }
+
+        return candidate.map(|(_, word)| word);
+    }
+
+    /** Returns a shortest word leading from a state to a final state. The transducer is trim,
+     * so there is one */
+    fn shortest_word_from(&self, q: usize) -> String {
+        let mut parents: HashMap<usize, Option<(usize, char)>> = HashMap::from([(q, None)]);
+        let mut queue = VecDeque::from([q]);
+
+        while let Some(p) = queue.pop_front() {
+            if self.is_final(p) {
+                let mut chars = Vec::new();
+                let mut next = parents[&p];
+                while let Some((parent, ch)) = next {
+                    chars.push(ch);
+                    next = parents[&parent];
+                }
+                return chars.into_iter().rev().collect();
+            }
+
+            for t in &self.state(p).transitions {
+                if let Entry::Vacant(entry) = parents.entry(t.to) {
+                    entry.insert(Some((p, t.ch)));
+                    queue.push_back(t.to);
+                }
+            }
+        }
+
+        panic!("The transducer must be trim!");
+    }
+}
+
+/** Returns the word leading to a pair, by following the parents back to the initial pair */
+fn path_to(parents: &HashMap<Pair, Option<(Pair, char)>>, pair: Pair) -> String {
+    let mut chars = Vec::new();
+    let mut next = parents[&pair];
+
+    while let Some((parent, ch)) = next {
+        chars.push(ch);
+        next = parents[&parent];
+    }
+
+    return chars.into_iter().rev().collect();
+}
+
+/** Both transducers are functions, so equivalent transducers must agree on the output
+ * that is common to all words through a pair. Only the rest is owed */
+fn normalise(left: Option<(usize, i128)>, right: Option<(usize, i128)>) -> Pair {
+    let common = left
+        .iter()
+        .chain(right.iter())
+        .map(|(_, o)| *o)
+        .min()
+        .unwrap_or(0);
+    let reduce = |side: Option<(usize, i128)>| side.map(|(q, o)| (q, o - common));
+    return (reduce(left), reduce(right));
+}
+
+/** Transducers are equal if they define the same function, independently of their states */
+impl PartialEq for Transducer {
+    fn eq(&self, other: &Self) -> bool {
+        return self.equivalent(other);
+    }
+}
+
+impl Eq for Transducer {}
@@ -0,0 +1,349 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt,
+};
+
+use super::{State, Transducer};
+
+/** A broken internal invariant of a transducer, found by `Transducer::validate` */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /** A state is referenced by some structure, but is not a live state */
+    UnknownState(usize),
+    /** A transition reads a character that is not in the alphabet */
+    UnknownChar(char),
+    /** The transitions of a state are not strictly sorted by their character */
+    UnsortedTransitions(usize),
+    /** A transition that is missing from the predecessors of its target */
+    MissingInverse { from: usize, ch: char, to: usize },
+    /** A predecessor of a state without a matching transition */
+    DanglingInverse { from: usize, ch: char, to: usize },
+    /** A live state in the free list, or a released id that is missing from it */
+    InconsistentFreeList(usize),
+    /** A state that cannot be reached from the initial state */
+    Unreachable(usize),
+    /** A state from which no final state can be reached */
+    Dead(usize),
+    /** A state whose outputs are not pushed towards the initial state */
+    NonCanonical { state: usize, min_output: i64 },
+    /** A state (outside of min_except) that is missing from the register */
+    MissingSignature(usize),
+    /** A state with the same signature as an already registered one */
+    DuplicateSignature { state: usize, equivalent: usize },
+    /** A register entry that does not match the current signature of its state */
+    StaleSignature(usize),
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantViolation::UnknownState(q) => write!(f, "state {} is not live", q),
+            InvariantViolation::UnknownChar(ch) => write!(f, "{:?} is not in the alphabet", ch),
+            InvariantViolation::UnsortedTransitions(q) => {
+                write!(f, "the transitions of state {} are not sorted", q)
+            }
+            InvariantViolation::MissingInverse { from, ch, to } => {
+                write!(
+                    f,
+                    "delta({}, {:?}) = {} is missing from the predecessors of {}",
+                    from, ch, to, to
+                )
+            }
+            InvariantViolation::DanglingInverse { from, ch, to } => {
+                write!(
+                    f,
+                    "state {} has the predecessor ({:?}, {}) which is not in delta",
+                    to, ch, from
+                )
+            }
+            InvariantViolation::InconsistentFreeList(q) => {
+                write!(f, "state {} is inconsistent with the free list", q)
+            }
+            InvariantViolation::Unreachable(q) => write!(f, "state {} is unreachable", q),
+            InvariantViolation::Dead(q) => write!(f, "state {} is dead", q),
+            InvariantViolation::NonCanonical { state, min_output } => {
+                write!(f, "state {} has a minimal output of {}", state, min_output)
+            }
+            InvariantViolation::MissingSignature(q) => {
+                write!(f, "state {} is not registered", q)
+            }
+            InvariantViolation::DuplicateSignature { state, equivalent } => {
+                write!(f, "state {} is equivalent to state {}", state, equivalent)
+            }
+            InvariantViolation::StaleSignature(q) => {
+                write!(f, "state {} is registered with a stale signature", q)
+            }
+        }
+    }
+}
+
+impl Transducer {
+    /** Checks the internal consistency of the transducer and returns every broken invariant */
+    pub fn validate(&self) -> Result<(), Vec<InvariantViolation>> {
+        let mut violations = Vec::new();
+
+        self.validate_transitions(&mut violations);
+        self.validate_free_list(&mut violations);
+        self.validate_trimness(&mut violations);
+        self.validate_canonicity(&mut violations);
+        self.validate_register(&mut violations);
+
+        if violations.is_empty() {
+            return Ok(());
+        }
+        return Err(violations);
+    }
+
+    /** Panics if the transducer is not consistent, used behind the check-invariants feature */
+    #[cfg(feature = "check-invariants")]
+    pub(super) fn assert_invariants(&self) {
+        if let Err(violations) = self.validate() {
+            let messages: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+            panic!("Broken transducer invariants:\n{}", messages.join("\n"));
+        }
+    }
+
+    fn validate_transitions(&self, violations: &mut Vec<InvariantViolation>) {
+        let mut unknown_states = BTreeSet::new();
+        let mut unknown_chars = BTreeSet::new();
+
+        if self.try_state(self.init_state).is_none() {
+            unknown_states.insert(self.init_state);
+        }
+
+        // States like the final sink have many predecessors, so they are looked up in a set
+        let inverse: HashSet<(usize, char, usize)> = self
+            .live_states()
+            .flat_map(|(to, state)| {
+                state
+                    .predecessors
+                    .iter()
+                    .map(move |&(ch, from)| (from, ch, to))
+            })
+            .collect();
+
+        for (from, state) in self.live_states() {
+            if !state.transitions.windows(2).all(|w| w[0].ch < w[1].ch) {
+                violations.push(InvariantViolation::UnsortedTransitions(from));
+            }
+
+            for t in &state.transitions {
+                if !self.alphabet.contains(&t.ch) {
+                    unknown_chars.insert(t.ch);
+                }
+
+                match self.try_state(t.to) {
+                    Some(_) if inverse.contains(&(from, t.ch, t.to)) => {}
+                    Some(_) => violations.push(InvariantViolation::MissingInverse {
+                        from,
+                        ch: t.ch,
+                        to: t.to,
+                    }),
+                    None => {
+                        unknown_states.insert(t.to);
+                    }
+                }
+            }
+
+            for &(ch, pred) in &state.predecessors {
+                let has_transition = self
+                    .try_state(pred)
+                    .and_then(|pred| pred.transition(ch))
+                    .is_some_and(|t| t.to == from);
+                if !has_transition {
+                    violations.push(InvariantViolation::DanglingInverse {
+                        from: pred,
+                        ch,
+                        to: from,
+                    });
+                }
+            }
+        }
+
+        violations.extend(
+            unknown_states
+                .into_iter()
+                .map(InvariantViolation::UnknownState),
+        );
+        violations.extend(
+            unknown_chars
+                .into_iter()
+                .map(InvariantViolation::UnknownChar),
+        );
+    }
+
+    /** The free list has to contain exactly the released ids, each one once */
+    fn validate_free_list(&self, violations: &mut Vec<InvariantViolation>) {
+        let mut free = HashSet::new();
+        let mut inconsistent = BTreeSet::new();
+
+        for q in &self.free {
+            if !free.insert(*q) {
+                inconsistent.insert(*q);
+            }
+        }
+
+        for (q, state) in self.states.iter().enumerate() {
+            if state.is_some() == free.contains(&q) {
+                inconsistent.insert(q);
+            }
+        }
+        inconsistent.extend(free.iter().filter(|q| **q >= self.states.len()));
+
+        violations.extend(
+            inconsistent
+                .into_iter()
+                .map(InvariantViolation::InconsistentFreeList),
+        );
+    }
+
+    /** Every state has to be reachable from the initial state and has to reach a final state */
+    fn validate_trimness(&self, violations: &mut Vec<InvariantViolation>) {
+        // The states are marked by their ids, which the transitions to unknown states exceed
+        let n = self.states.len().max(self.init_state + 1);
+        let mut reachable = vec![false; n];
+        reachable[self.init_state] = true;
+        let mut stack = vec![self.init_state];
+
+        while let Some(q) = stack.pop() {
+            for t in self.try_state(q).into_iter().flat_map(|s| &s.transitions) {
+                if t.to < n && !reachable[t.to] {
+                    reachable[t.to] = true;
+                    stack.push(t.to);
+                }
+            }
+        }
+
+        // Walk the transitions backwards (without trusting the predecessors)
+        // from the final states
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+        for (from, state) in self.live_states() {
+            for t in state.transitions.iter().filter(|t| t.to < n) {
+                predecessors[t.to].push(from);
+            }
+        }
+
+        let mut stack: Vec<usize> = self
+            .live_states()
+            .filter(|(_, state)| state.is_final())
+            .map(|(q, _)| q)
+            .collect();
+
+        // The initial state of an empty transducer is the only state allowed to be dead
+        if stack.is_empty() {
+            stack.push(self.init_state);
+        }
+        let mut alive = vec![false; n];
+        for q in &stack {
+            alive[*q] = true;
+        }
+
+        while let Some(q) = stack.pop() {
+            for prev in &predecessors[q] {
+                if !alive[*prev] {
+                    alive[*prev] = true;
+                    stack.push(*prev);
+                }
+            }
+        }
+
+        for (q, _) in self.live_states() {
+            if !reachable[q] {
+                violations.push(InvariantViolation::Unreachable(q));
+            }
+            if !alive[q] {
+                violations.push(InvariantViolation::Dead(q));
+            }
+        }
+    }
+
+    /** The minimal output leaving a state (including its final output) has to be 0 */
+    fn validate_canonicity(&self, violations: &mut Vec<InvariantViolation>) {
+        for (q, state) in self.live_states() {
+            let min_output = state
+                .transitions
+                .iter()
+                .map(|t| t.output)
+                .chain(state.final_output)
+                .min();
+
+            if let Some(min_output) = min_output {
+                if min_output != 0 {
+                    violations.push(InvariantViolation::NonCanonical {
+                        state: q,
+                        min_output,
+                    });
+                }
+            }
+        }
+    }
+
+    /** Every state outside of the min_except path has to be registered with its signature.
+     * The initial state is on that path only while min_except is not epsilon */
+    fn validate_register(&self, violations: &mut Vec<InvariantViolation>) {
+        let except_path: HashSet<usize> = if self.min_except.is_empty() {
+            HashSet::new()
+        } else {
+            self.try_state_sequence(&self.min_except)
+                .into_iter()
+                .collect()
+        };
+
+        for (q, state) in self.live_states() {
+            if except_path.contains(&q) {
+                continue;
+            }
+
+            let q_eq = self.register.find(state.signature_hash(), |q_eq| {
+                q_eq == q
+                    || self
+                        .try_state(q_eq)
+                        .is_some_and(|s| s.has_same_signature(state))
+            });
+
+            match q_eq {
+                Some(q_eq) if q_eq == q => {}
+                Some(q_eq) => violations.push(InvariantViolation::DuplicateSignature {
+                    state: q,
+                    equivalent: q_eq,
+                }),
+                None => violations.push(InvariantViolation::MissingSignature(q)),
+            }
+        }
+
+        let mut stale: Vec<usize> = self
+            .register
+            .iter()
+            .filter(|(hash, q)| match self.try_state(*q) {
+                Some(state) => except_path.contains(q) || state.signature_hash() != *hash,
+                None => true,
+            })
+            .map(|(_, q)| q)
+            .collect();
+        stale.sort();
+        violations.extend(stale.into_iter().map(InvariantViolation::StaleSignature));
+    }
+
+    /** Returns a live state, or None for a released or an unknown id */
+    fn try_state(&self, q: usize) -> Option<&State> {
+        return self.states.get(q).and_then(|state| state.as_ref());
+    }
+
+    /** Like state_sequence, but stops at the first missing transition instead of panicking */
+    fn try_state_sequence(&self, w: &[char]) -> Vec<usize> {
+        let mut next = self.init_state;
+        let mut path = vec![next];
+
+        for ch in w {
+            match self.try_state(next).and_then(|state| state.next(*ch)) {
+                Some(q) => {
+                    next = q;
+                    path.push(next);
+                }
+                None => break,
+            }
+        }
+
+        return path;
+    }
+}
@@ -0,0 +1,137 @@
+use std::{error::Error, fmt, iter};
+
+use super::{StringTransducer, Transducer};
+
+/** How the outputs of a transducer are written as the words of its inverse */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyEncoding {
+    /** The decimal digits of the output */
+    #[default]
+    Decimal,
+    /** The given number of big-endian bytes of the output, as the chars U+0000 to U+00FF.
+     * The outputs are shifted by half of the range of the bytes, so that the keys of negative
+     * outputs come first and the keys sort like the outputs */
+    FixedWidth(usize),
+}
+
+impl KeyEncoding {
+    /** Returns the key of an output, or None if it does not fit into the fixed width */
+    pub fn encode(&self, output: i64) -> Option<String> {
+        match self {
+            KeyEncoding::Decimal => return Some(output.to_string()),
+            KeyEncoding::FixedWidth(0) => return None,
+            KeyEncoding::FixedWidth(width) => {
+                // Wider keys than the output are padded with zeros
+                let n = (*width).min(8);
+                let half = 1i128 << (8 * n - 1);
+                let shifted = i128::from(output) + half;
+
+                if shifted < 0 || shifted >= 2 * half {
+                    return None;
+                }
+
+                let bytes = (shifted as u64).to_be_bytes();
+                return Some(
+                    iter::repeat_n('\0', width - n)
+                        .chain(bytes[8 - n..].iter().map(|b| char::from(*b)))
+                        .collect(),
+                );
+            }
+        }
+    }
+}
+
+/** The outputs of a transducer cannot be the words of its inverse */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InversionError {
+    /** Two words have the same output, so the inverse is not a function */
+    NotInjective {
+        output: i64,
+        word: String,
+        other: String,
+    },
+    /** An output does not fit into the fixed width of the keys */
+    TooWide { output: i64, width: usize },
+}
+
+impl fmt::Display for InversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InversionError::NotInjective {
+                output,
+                word,
+                other,
+            } => write!(
+                f,
+                "the transducer is not injective: {:?} and {:?} have the output {}",
+                word, other, output
+            ),
+            InversionError::TooWide { output, width } => {
+                write!(f, "the output {} does not fit into {} bytes", output, width)
+            }
+        }
+    }
+}
+
+impl Error for InversionError {}
+
+impl Transducer {
+    /** Constructs the minimal transducer from the encoded output of every word to the rank of
+     * the word, i.e. its 0-based position in lexicographic order. Fails if two words have the
+     * same output */
+    pub fn invert(&self, encoding: KeyEncoding) -> Result<Transducer, InversionError> {
+        let keys = self.inverse_keys(encoding)?;
+        if keys.is_empty() {
+            return Ok(Transducer::empty());
+        }
+
+        return Ok(Transducer::from_sorted_iter(
+            keys.iter().map(|(key, rank, _)| (key, *rank as i64)),
+        ));
+    }
+
+    /** Like invert, but maps the encoded outputs back to the words themselves */
+    pub fn invert_to_words(
+        &self,
+        encoding: KeyEncoding,
+    ) -> Result<StringTransducer, InversionError> {
+        let keys = self.inverse_keys(encoding)?;
+        return Ok(StringTransducer::from_pairs(
+            keys.into_iter().map(|(key, _, word)| (key, word)),
+        ));
+    }
+
+    /** Returns the (key, rank, word) triples of all entries sorted by their keys, checking that
+     * no two words share an output */
+    fn inverse_keys(
+        &self,
+        encoding: KeyEncoding,
+    ) -> Result<Vec<(String, usize, String)>, InversionError> {
+        let mut keys = Vec::with_capacity(self.get_number_of_final_states());
+
+        for (rank, (word, output)) in self.entries().enumerate() {
+            let Some(key) = encoding.encode(output) else {
+                let KeyEncoding::FixedWidth(width) = encoding else {
+                    unreachable!("Every output has a decimal encoding");
+                };
+                return Err(InversionError::TooWide { output, width });
+            };
+            keys.push((key, rank, word));
+        }
+
+        keys.sort();
+
+        for pair in keys.windows(2) {
+            let ((key, _, word), (next_key, _, next_word)) = (&pair[0], &pair[1]);
+            if key == next_key {
+                return Err(InversionError::NotInjective {
+                    output: self.get(word).unwrap(),
+                    word: word.clone(),
+                    other: next_word.clone(),
+                });
+            }
+        }
+
+        return Ok(keys);
+    }
+}
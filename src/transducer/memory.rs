@@ -0,0 +1,79 @@
+use std::{fmt, mem::size_of};
+
+use super::{State, Transducer, Transition};
+
+/** The bytes allocated by the structures of a transducer. Vectors are counted by their
+ * capacity and hash tables by their capacity times the size of an entry plus a control byte,
+ * so the numbers are estimates of the heap usage, not exact allocator statistics */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryStats {
+    /** The slots of the state arena, with the inline final outputs (psi) */
+    pub states: usize,
+    /** The sorted transitions with their inline outputs (delta and lambda) */
+    pub transitions: usize,
+    /** The ingoing transitions of the states (delta_inv) */
+    pub predecessors: usize,
+    /** The released ids waiting to be reused */
+    pub free_list: usize,
+    /** The hash-consed signatures of the registered states */
+    pub register: usize,
+    pub alphabet: usize,
+    pub min_except: usize,
+    /** The size of the form written by save, which is not part of the total */
+    pub serialised: usize,
+}
+
+impl MemoryStats {
+    /** The bytes used in memory, including the transducer itself */
+    pub fn total(&self) -> usize {
+        return size_of::<Transducer>()
+            + self.states
+            + self.transitions
+            + self.predecessors
+            + self.free_list
+            + self.register
+            + self.alphabet
+            + self.min_except;
+    }
+}
+
+impl fmt::Display for MemoryStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "States: {} bytes", self.states)?;
+        writeln!(f, "Transitions: {} bytes", self.transitions)?;
+        writeln!(f, "Predecessors: {} bytes", self.predecessors)?;
+        writeln!(f, "Free list: {} bytes", self.free_list)?;
+        writeln!(f, "Register: {} bytes", self.register)?;
+        writeln!(f, "Alphabet: {} bytes", self.alphabet)?;
+        writeln!(f, "Min except: {} bytes", self.min_except)?;
+        writeln!(f, "Total: {} bytes", self.total())?;
+        return write!(f, "Serialised: {} bytes", self.serialised);
+    }
+}
+
+impl Transducer {
+    /** Reports the bytes used by each internal structure and by the serialised form */
+    pub fn memory_stats(&self) -> MemoryStats {
+        let live = || self.states.iter().flatten();
+
+        return MemoryStats {
+            states: self.states.capacity() * size_of::<Option<State>>(),
+            transitions: live()
+                .map(|state| state.transitions.capacity() * size_of::<Transition>())
+                .sum(),
+            predecessors: live()
+                .map(|state| state.predecessors.capacity() * size_of::<(char, usize)>())
+                .sum(),
+            free_list: self.free.capacity() * size_of::<usize>(),
+            register: self.register.memory_usage(),
+            alphabet: hash_table_bytes::<char>(self.alphabet.capacity()),
+            min_except: self.min_except.capacity() * size_of::<char>(),
+            serialised: self.serialised_size(),
+        };
+    }
+}
+
+/** Estimates the bytes of a hash table with the given capacity and entry type */
+pub(super) fn hash_table_bytes<T>(capacity: usize) -> usize {
+    return capacity * (size_of::<T>() + 1);
+}
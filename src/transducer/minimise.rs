@@ -0,0 +1,184 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::{
+    overflow::{difference, narrow, sum},
+    Register, State, Transducer,
+};
+
+impl Transducer {
+    /** Constructs the minimal transducer for the same function from scratch, independently of
+     * the incrementally maintained register. The outputs are pushed towards the initial state
+     * and then the states are merged by partition refinement over their signatures */
+    pub fn minimised(&self) -> Transducer {
+        let pushed = self.pushed_outputs();
+
+        let Some(&init_output) = pushed.get(&self.init_state) else {
+            return Transducer::from_parts(1, Vec::new(), Vec::new(), 0);
+        };
+
+        let mut live: Vec<usize> = pushed.keys().copied().collect();
+        live.sort();
+
+        // Canonical outputs of the live transitions and final states
+        let mut transitions: HashMap<usize, Vec<(char, usize, i64)>> = HashMap::new();
+        for q in &live {
+            let trans: Vec<(char, usize, i64)> = self
+                .state(*q)
+                .transitions
+                .iter()
+                .filter_map(|t| {
+                    let dest_output = pushed.get(&t.to)?;
+                    let output = narrow(
+                        i128::from(t.output) + i128::from(*dest_output) - i128::from(pushed[q]),
+                    );
+                    Some((t.ch, t.to, output))
+                })
+                .collect();
+            transitions.insert(*q, trans);
+        }
+        let final_output = |q: &usize| {
+            self.state(*q)
+                .final_output
+                .map(|o| difference(o, pushed[q]))
+        };
+
+        // Moore-style refinement: start with a single block and split blocks by signature
+        // until the number of blocks stops growing
+        let mut block: HashMap<usize, usize> = live.iter().map(|q| (*q, 0)).collect();
+        let mut num_blocks = 1;
+
+        loop {
+            let mut block_by_signature: HashMap<(usize, Option<i64>, Vec<_>), usize> =
+                HashMap::new();
+            let mut next_block = HashMap::with_capacity(live.len());
+
+            for q in &live {
+                let sig_transitions: Vec<(char, usize, i64)> = transitions[q]
+                    .iter()
+                    .map(|(ch, q_dest, o)| (*ch, block[q_dest], *o))
+                    .collect();
+                let signature = (block[q], final_output(q), sig_transitions);
+
+                let n = block_by_signature.len();
+                let b = *block_by_signature.entry(signature).or_insert(n);
+                next_block.insert(*q, b);
+            }
+
+            block = next_block;
+            if block_by_signature.len() == num_blocks {
+                break;
+            }
+            num_blocks = block_by_signature.len();
+        }
+
+        // Number the blocks in BFS order from the initial state
+        let mut ids: HashMap<usize, usize> = HashMap::from([(block[&self.init_state], 0)]);
+        let mut queue = VecDeque::from([self.init_state]);
+        let mut quotient_transitions = Vec::new();
+        let mut finals = Vec::new();
+
+        while let Some(q) = queue.pop_front() {
+            let id = ids[&block[&q]];
+
+            if let Some(o) = final_output(&q) {
+                finals.push((id, o));
+            }
+
+            for (ch, q_dest, o) in &transitions[&q] {
+                let n = ids.len();
+                let dest_id = *ids.entry(block[q_dest]).or_insert_with(|| {
+                    queue.push_back(*q_dest);
+                    n
+                });
+                quotient_transitions.push((id, *ch, dest_id, *o));
+            }
+        }
+
+        return Transducer::from_parts(
+            ids.len(),
+            quotient_transitions,
+            finals,
+            sum(self.iota, init_output),
+        );
+    }
+
+    /** Checks that no state can be merged with another one, by comparing the number of states
+     * with the one of the batch minimised transducer */
+    pub fn is_minimal(&self) -> bool {
+        return self.minimised().get_number_of_states() == self.get_number_of_states();
+    }
+
+    /** Constructs a transducer, minimal except in epsilon, from numbered states 0..n,
+     * where 0 is the initial state. Transitions are given as (from, char, to, output) */
+    pub(super) fn from_parts(
+        n: usize,
+        transitions: Vec<(usize, char, usize, i64)>,
+        finals: Vec<(usize, i64)>,
+        iota: i64,
+    ) -> Self {
+        let mut transducer = Transducer {
+            alphabet: HashSet::new(),
+            states: (0..n).map(|_| Some(State::default())).collect(),
+            free: Vec::new(),
+            init_state: 0,
+            iota,
+            min_except: Vec::new(),
+            register: Register::with_capacity(n),
+            compaction_threshold: None,
+            ranked: false,
+        };
+
+        for (q1, a, q2, o) in transitions {
+            transducer.alphabet.insert(a);
+            transducer.add_delta_transition(q1, a, q2, o);
+        }
+
+        for (q, o) in finals {
+            transducer.state_mut(q).final_output = Some(o);
+        }
+
+        for q in 0..n {
+            transducer.add_signature(q);
+        }
+
+        return transducer;
+    }
+
+    /** Finds the minimal output of the language of every state, from which a final state can be
+     * reached. The delta graph is acyclic, so the outputs are computed in post-order */
+    fn pushed_outputs(&self) -> HashMap<usize, i64> {
+        let mut pushed: HashMap<usize, Option<i64>> = HashMap::new();
+        let mut stack = vec![(self.init_state, false)];
+
+        while let Some((q, is_expanded)) = stack.pop() {
+            if pushed.contains_key(&q) {
+                continue;
+            }
+
+            let state = self.state(q);
+
+            if !is_expanded {
+                stack.push((q, true));
+                for t in &state.transitions {
+                    if !pushed.contains_key(&t.to) {
+                        stack.push((t.to, false));
+                    }
+                }
+                continue;
+            }
+
+            let min_output = state
+                .transitions
+                .iter()
+                .filter_map(|t| pushed[&t.to].map(|o| sum(t.output, o)))
+                .chain(state.final_output)
+                .min();
+            pushed.insert(q, min_output);
+        }
+
+        return pushed
+            .into_iter()
+            .filter_map(|(q, o)| o.map(|o| (q, o)))
+            .collect();
+    }
+}
@@ -0,0 +1,116 @@
+use std::{error::Error, fmt};
+
+use super::Transducer;
+
+/** An edit would make an output of the transducer overflow. The outputs of the transitions are
+ * differences between the outputs of words, so they may not fit into an i64 even if the outputs
+ * of all words do */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverflowError {
+    pub word: String,
+    /** The new output of the word, or None if it is removed */
+    pub output: Option<i64>,
+}
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.output {
+            Some(output) => write!(
+                f,
+                "the outputs overflow when {:?} gets the output {}",
+                self.word, output
+            ),
+            None => write!(f, "the outputs overflow when {:?} is removed", self.word),
+        }
+    }
+}
+
+impl Error for OverflowError {}
+
+/** Adds two outputs, panicking instead of wrapping around. The edits are checked before they
+ * change the transducer, so this only fails on a bug or on transducers combined from others */
+pub(super) fn sum(a: i64, b: i64) -> i64 {
+    return a.checked_add(b).expect("Output overflow!");
+}
+
+/** Subtracts two outputs, panicking instead of wrapping around, like sum */
+pub(super) fn difference(a: i64, b: i64) -> i64 {
+    return a.checked_sub(b).expect("Output overflow!");
+}
+
+/** Narrows an intermediate output, which was computed in i128 so that it cannot overflow */
+pub(super) fn narrow(output: i128) -> i64 {
+    return i64::try_from(output).expect("Output overflow!");
+}
+
+impl Transducer {
+    /** Checks that giving a word a new output, or removing it with None, keeps all outputs in
+     * range, without changing the transducer. Computes the new minimal output of every state
+     * on the path of the word from the absolute outputs of the other words through it, and
+     * checks that the new outputs of the state, i.e. the differences to its minimum, fit */
+    pub(super) fn check_edit(&self, word: &str, output: Option<i64>) -> Result<(), OverflowError> {
+        let error = || OverflowError {
+            word: word.to_string(),
+            output,
+        };
+
+        // The states along the existing prefix of the word, with the minimal output through them
+        // and the next character of the word, if there is one
+        let mut path = Vec::new();
+        let mut next = Some((self.init_state, self.iota));
+        for ch in word.chars() {
+            let Some((q, prefix_output)) = next else {
+                break;
+            };
+            path.push((q, prefix_output, Some(ch)));
+            next = self
+                .state(q)
+                .transition(ch)
+                .map(|t| (t.to, sum(prefix_output, t.output)));
+        }
+        if let Some((q, prefix_output)) = next {
+            path.push((q, prefix_output, None));
+        }
+
+        // The minimal outputs of the other words and of all words after the state. After the
+        // last state on the path only the word itself is left
+        let mut others_below: Option<i64> = None;
+        let mut min_below: Option<i64> = output;
+
+        for (q, prefix_output, next_char) in path.into_iter().rev() {
+            let state = self.state(q);
+
+            // The word itself is the final output of its last state
+            let others = state
+                .transitions
+                .iter()
+                .filter(|t| Some(t.ch) != next_char)
+                .map(|t| t.output)
+                .chain(state.final_output.filter(|_| next_char.is_some()));
+            let (min_other, max_other) = others.fold((None, None), |(min, max), o| {
+                return (
+                    Some(min.map_or(o, |min: i64| min.min(o))),
+                    Some(max.map_or(o, |max: i64| max.max(o))),
+                );
+            });
+            let absolute = |o: Option<i64>| match o {
+                Some(o) => return prefix_output.checked_add(o).map(Some).ok_or_else(error),
+                None => return Ok(None),
+            };
+            let (min_other, max_other) = (absolute(min_other)?, absolute(max_other)?);
+
+            others_below = min_other.into_iter().chain(others_below).min();
+            let new_min = others_below.into_iter().chain(output).min();
+
+            // The new outputs are at least the new minimum, so only the largest ones can overflow
+            if let Some(new_min) = new_min {
+                for o in max_other.into_iter().chain(min_below) {
+                    o.checked_sub(new_min).ok_or_else(error)?;
+                }
+            }
+            min_below = new_min;
+        }
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,220 @@
+use std::{collections::HashMap, thread};
+
+use super::{overflow::sum, BuildError, Builder, DuplicatePolicy, Transducer};
+
+/** The final output and the (char, state, output) transitions of a state, with the states
+ * already renumbered to their equivalence classes */
+type MergedSignature = (Option<i64>, Vec<(char, usize, i64)>);
+
+impl Transducer {
+    /** Like from_sorted_iter, but builds key ranges of the entries on separate threads.
+     * Panics on invalid input */
+    pub fn from_sorted_slice_parallel<K>(entries: &[(K, i64)], threads: usize) -> Self
+    where
+        K: AsRef<str> + Sync,
+    {
+        match Self::try_from_sorted_slice_parallel(entries, DuplicatePolicy::Reject, threads) {
+            Ok(transducer) => return transducer,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /** Like try_from_sorted_iter, but splits the entries into at most `threads` key ranges by
+     * their first character and builds a transducer for each range on its own thread. The
+     * transducers are then merged under a shared root, merging their equivalent states, so the
+     * result is the same minimal transducer as the one of the sequential construction */
+    pub fn try_from_sorted_slice_parallel<K>(
+        entries: &[(K, i64)],
+        policy: DuplicatePolicy,
+        threads: usize,
+    ) -> Result<Self, BuildError>
+    where
+        K: AsRef<str> + Sync,
+    {
+        let bounds = shard_bounds(entries, threads);
+
+        let shards: Vec<Result<Transducer, BuildError>> = thread::scope(|scope| {
+            let handles: Vec<_> = bounds
+                .windows(2)
+                .map(|w| {
+                    let (start, end) = (w[0], w[1]);
+                    scope.spawn(move || build_shard(&entries[start..end], policy, start))
+                })
+                .collect();
+
+            return handles
+                .into_iter()
+                .map(|handle| handle.join().expect("Building a shard panicked!"))
+                .collect();
+        });
+
+        // Outputs overflow depending on the entries before, which the shards do not see, so the
+        // sequential construction finds the first entry whose outputs overflow
+        let sequential = || {
+            return Self::try_from_sorted_iter(
+                entries.iter().map(|(w, o)| (w.as_ref(), *o)),
+                policy,
+            );
+        };
+
+        // Report the first invalid entry, like the sequential construction would
+        let mut transducers = Vec::with_capacity(shards.len());
+        for (i, shard) in shards.into_iter().enumerate() {
+            let start = bounds[i];
+
+            if i > 0 {
+                let previous = entries[start - 1].0.as_ref();
+                let word = entries[start].0.as_ref();
+
+                if !word.is_empty() && previous > word {
+                    return Err(BuildError::OutOfOrder {
+                        line: start + 1,
+                        word: word.to_string(),
+                        previous: previous.to_string(),
+                    });
+                }
+            }
+
+            match shard {
+                Err(BuildError::Overflow { .. }) => return sequential(),
+                shard => transducers.push(shard?),
+            }
+        }
+
+        if transducers.len() == 1 {
+            return Ok(transducers.pop().unwrap());
+        }
+
+        match Transducer::merge_shards(&transducers) {
+            Some(transducer) => return Ok(transducer),
+            None => return sequential(),
+        }
+    }
+
+    /** Puts the initial transitions of all shards under a new root and merges the equivalent
+     * states of different shards bottom-up with a shared register. Every shard is minimal,
+     * so its states are in canonical form and equivalent states have equal signatures.
+     * Returns None if the outputs of the root overflow */
+    fn merge_shards(shards: &[Transducer]) -> Option<Transducer> {
+        // The root keeps the id 0
+        let mut ids: HashMap<MergedSignature, usize> = HashMap::new();
+        let mut transitions = Vec::new();
+        let mut finals = Vec::new();
+        let mut root_transitions = Vec::new();
+
+        for shard in shards {
+            let mut merged_ids: HashMap<usize, usize> = HashMap::new();
+            let mut stack = vec![(shard.init_state, false)];
+
+            // Visit the states in post-order, so that the targets are merged first
+            while let Some((q, is_expanded)) = stack.pop() {
+                if merged_ids.contains_key(&q) {
+                    continue;
+                }
+
+                let state = shard.state(q);
+
+                if !is_expanded {
+                    stack.push((q, true));
+                    for t in &state.transitions {
+                        if !merged_ids.contains_key(&t.to) {
+                            stack.push((t.to, false));
+                        }
+                    }
+                    continue;
+                }
+
+                let signature: MergedSignature = (
+                    state.final_output,
+                    state
+                        .transitions
+                        .iter()
+                        .map(|t| (t.ch, merged_ids[&t.to], t.output))
+                        .collect(),
+                );
+
+                if q == shard.init_state {
+                    for (ch, to, output) in signature.1 {
+                        root_transitions.push((ch, to, sum(shard.iota, output)));
+                    }
+                    merged_ids.insert(q, 0);
+                    continue;
+                }
+
+                let n = ids.len() + 1;
+                let id = *ids
+                    .entry(signature)
+                    .or_insert_with_key(|(final_output, trans)| {
+                        if let Some(o) = final_output {
+                            finals.push((n, *o));
+                        }
+                        for (ch, to, output) in trans {
+                            transitions.push((n, *ch, *to, *output));
+                        }
+                        n
+                    });
+                merged_ids.insert(q, id);
+            }
+        }
+
+        // Push the minimal output of the root into iota
+        let iota = root_transitions
+            .iter()
+            .map(|(_, _, o)| *o)
+            .min()
+            .unwrap_or(0);
+        for (ch, to, output) in root_transitions {
+            transitions.push((0, ch, to, output.checked_sub(iota)?));
+        }
+
+        return Some(Transducer::from_parts(
+            ids.len() + 1,
+            transitions,
+            finals,
+            iota,
+        ));
+    }
+}
+
+/** Splits the entries into at most `threads` ranges of similar sizes, moving every bound
+ * forward until the first character changes. Returns the bounds including 0 and the length */
+fn shard_bounds<K: AsRef<str>>(entries: &[(K, i64)], threads: usize) -> Vec<usize> {
+    let first_char = |i: usize| entries[i].0.as_ref().chars().next();
+    let mut bounds = vec![0];
+
+    for k in 1..threads.max(1) {
+        let mut bound = (k * entries.len() / threads).max(bounds[bounds.len() - 1] + 1);
+
+        while bound < entries.len() && first_char(bound) == first_char(bound - 1) {
+            bound += 1;
+        }
+
+        if bound >= entries.len() {
+            break;
+        }
+        bounds.push(bound);
+    }
+
+    bounds.push(entries.len());
+    return bounds;
+}
+
+/** Builds the transducer of a key range, reporting errors with their line in all entries */
+fn build_shard<K: AsRef<str>>(
+    entries: &[(K, i64)],
+    policy: DuplicatePolicy,
+    offset: usize,
+) -> Result<Transducer, BuildError> {
+    let mut builder = Builder::with_capacity(policy, entries.len());
+    let at_line = |e: BuildError| match e.line() {
+        Some(line) => e.at_line(offset + line),
+        None => e,
+    };
+
+    for (w, o) in entries {
+        builder.add(w.as_ref(), *o).map_err(at_line)?;
+    }
+
+    // The last entry is only added by finish
+    return builder.finish().map_err(at_line);
+}
@@ -0,0 +1,235 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+};
+
+use super::{
+    overflow::{difference, sum},
+    Transducer,
+};
+
+/** Decides the output of a word that is in both transducers of a union */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    #[default]
+    Left,
+    Right,
+    Min,
+    Max,
+    Sum,
+}
+
+impl ConflictPolicy {
+    /** Combines the outputs of a word in the left and in the right transducer */
+    pub fn combine(&self, left: i64, right: i64) -> i64 {
+        match self {
+            ConflictPolicy::Left => left,
+            ConflictPolicy::Right => right,
+            ConflictPolicy::Min => left.min(right),
+            ConflictPolicy::Max => left.max(right),
+            ConflictPolicy::Sum => sum(left, right),
+        }
+    }
+
+    /** How often an output emitted for both transducers counts in the combined output */
+    fn multiplicity(&self) -> i64 {
+        match self {
+            ConflictPolicy::Sum => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(ConflictPolicy::Left),
+            "right" => Ok(ConflictPolicy::Right),
+            "min" => Ok(ConflictPolicy::Min),
+            "max" => Ok(ConflictPolicy::Max),
+            "sum" => Ok(ConflictPolicy::Sum),
+            _ => Err(format!("unknown conflict policy {:?}", s)),
+        }
+    }
+}
+
+/** The combination computed by the synchronised walk */
+enum Operation<'a> {
+    Union(ConflictPolicy),
+    Intersection(&'a dyn Fn(i64, i64) -> i64),
+    Difference,
+}
+
+/** A state of the synchronised walk: the states reached in the left and the right transducer
+ * with the outputs still owed to their words, and the extra output owed to the words of both.
+ * The owed outputs are reduced by the output emitted so far, so that equal pairs are shared */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Pair {
+    left: Option<(usize, i64)>,
+    right: Option<(usize, i64)>,
+    both: i64,
+}
+
+impl Transducer {
+    /** Constructs the minimal transducer with the entries of both transducers, resolving the
+     * outputs of words in both with the policy. The transition graphs are walked in parallel
+     * instead of inserting the entries of one transducer into the other.
+     * Panics if a combined output overflows */
+    pub fn union(left: &Transducer, right: &Transducer, policy: ConflictPolicy) -> Transducer {
+        return Transducer::product(left, right, Operation::Union(policy));
+    }
+
+    /** Constructs the minimal transducer with the words in both transducers, whose outputs are
+     * combined by the given function of the left and the right output */
+    pub fn intersection<F>(left: &Transducer, right: &Transducer, combine: F) -> Transducer
+    where
+        F: Fn(i64, i64) -> i64,
+    {
+        return Transducer::product(left, right, Operation::Intersection(&combine));
+    }
+
+    /** Constructs the minimal transducer with the entries of the left transducer,
+     * whose words are not in the right transducer */
+    pub fn difference(left: &Transducer, right: &Transducer) -> Transducer {
+        return Transducer::product(left, right, Operation::Difference);
+    }
+
+    /** Walks the pairs of states reached by the same words in both transducers, following
+     * only the pairs that can still lead to words of the result */
+    fn product(left: &Transducer, right: &Transducer, operation: Operation) -> Transducer {
+        let root = |t: &Transducer| (!t.is_empty()).then_some((t.init_state, t.iota));
+        let (left_root, right_root) = (root(left), root(right));
+
+        if !operation.follows(left_root, right_root) {
+            return Transducer::empty();
+        }
+        let (iota, init) = operation.normalise(left_root, right_root, 0);
+
+        let mut ids = HashMap::from([(init, 0)]);
+        let mut queue = VecDeque::from([init]);
+        let mut transitions = Vec::new();
+        let mut finals = Vec::new();
+
+        while let Some(pair) = queue.pop_front() {
+            let id = ids[&pair];
+
+            let final_left = pair
+                .left
+                .and_then(|(q, o)| left.state(q).final_output.map(|f| sum(o, f)));
+            let final_right = pair
+                .right
+                .and_then(|(q, o)| right.state(q).final_output.map(|f| sum(o, f)));
+            if let Some(o) = operation.final_output(final_left, final_right, pair.both) {
+                finals.push((id, o));
+            }
+
+            let mut chars: Vec<char> = pair
+                .left
+                .into_iter()
+                .flat_map(|(q, _)| left.state(q).transitions.iter().map(|t| t.ch))
+                .chain(
+                    pair.right
+                        .into_iter()
+                        .flat_map(|(q, _)| right.state(q).transitions.iter().map(|t| t.ch)),
+                )
+                .collect();
+            chars.sort();
+            chars.dedup();
+
+            for ch in chars {
+                let step = |t: &Transducer, side: Option<(usize, i64)>| {
+                    let (q, o) = side?;
+                    let transition = t.state(q).transition(ch)?;
+                    Some((transition.to, sum(o, transition.output)))
+                };
+                let (next_left, next_right) = (step(left, pair.left), step(right, pair.right));
+
+                if !operation.follows(next_left, next_right) {
+                    continue;
+                }
+                let (output, next) = operation.normalise(next_left, next_right, pair.both);
+
+                let n = ids.len();
+                let next_id = *ids.entry(next).or_insert_with(|| {
+                    queue.push_back(next);
+                    n
+                });
+                transitions.push((id, ch, next_id, output));
+            }
+        }
+
+        // The walk is deterministic but neither minimal nor trim, e.g. if both transducers
+        // share suffixes after different prefixes or a pair of the intersection reaches
+        // no word of both
+        let product = Transducer::from_parts(ids.len(), transitions, finals, iota);
+        return product.minimised();
+    }
+}
+
+impl Operation<'_> {
+    /** Checks if a pair of states (None for no state) can lead to words of the result */
+    fn follows(&self, left: Option<(usize, i64)>, right: Option<(usize, i64)>) -> bool {
+        match self {
+            Operation::Union(_) => return left.is_some() || right.is_some(),
+            Operation::Intersection(_) => return left.is_some() && right.is_some(),
+            Operation::Difference => return left.is_some(),
+        }
+    }
+
+    /** The output of a word ending in a pair of states with the given final outputs */
+    fn final_output(&self, left: Option<i64>, right: Option<i64>, both: i64) -> Option<i64> {
+        match (self, left, right) {
+            (Operation::Union(policy), Some(l), Some(r)) => {
+                return Some(sum(policy.combine(l, r), both))
+            }
+            (Operation::Union(_), l, r) => return l.or(r),
+            (Operation::Intersection(combine), Some(l), Some(r)) => return Some(combine(l, r)),
+            (Operation::Intersection(_), _, _) => return None,
+            (Operation::Difference, Some(l), None) => return Some(l),
+            (Operation::Difference, _, _) => return None,
+        }
+    }
+
+    /** Emits the part of the owed outputs that all words of the pair share and returns it
+     * with the reduced pair. The combination of an intersection is an arbitrary function,
+     * so its pairs keep the whole owed outputs */
+    fn normalise(
+        &self,
+        left: Option<(usize, i64)>,
+        right: Option<(usize, i64)>,
+        both: i64,
+    ) -> (i64, Pair) {
+        let emitted = match self {
+            Operation::Union(_) => left.iter().chain(right.iter()).map(|(_, o)| *o).min(),
+            Operation::Intersection(_) => None,
+            Operation::Difference => left.map(|(_, o)| o),
+        }
+        .unwrap_or(0);
+        let reduce = |side: Option<(usize, i64)>| side.map(|(q, o)| (q, difference(o, emitted)));
+
+        // An emitted output that counts twice for the words of both is owed once more
+        let both = match (self, left, right) {
+            (Operation::Union(policy), Some(_), Some(_)) => {
+                sum(both, emitted * (policy.multiplicity() - 1))
+            }
+            _ => 0,
+        };
+
+        // The outputs of the right transducer do not matter for a difference
+        let right = match self {
+            Operation::Difference => right.map(|(q, _)| (q, 0)),
+            _ => reduce(right),
+        };
+
+        return (
+            emitted,
+            Pair {
+                left: reduce(left),
+                right,
+                both,
+            },
+        );
+    }
+}
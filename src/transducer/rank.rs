@@ -0,0 +1,154 @@
+use super::{overflow::sum, BuildError, DuplicatePolicy, Transducer};
+
+impl Transducer {
+    /** Constructs a minimal perfect hash of lexicographically sorted words, mapping every word
+     * to its 0-based rank, in rank mode. Panics on invalid input */
+    pub fn from_sorted_words<I, K>(words: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        match Self::try_from_sorted_words(words) {
+            Ok(transducer) => return transducer,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /** Like from_sorted_words, but reports invalid input as an error. Repeated words are
+     * rejected, since they would get different ranks */
+    pub fn try_from_sorted_words<I, K>(words: I) -> Result<Self, BuildError>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        let entries = words
+            .into_iter()
+            .enumerate()
+            .map(|(rank, w)| (w, rank as i64));
+        let mut transducer = Self::try_from_sorted_iter(entries, DuplicatePolicy::Reject)?;
+        transducer.ranked = true;
+        return Ok(transducer);
+    }
+
+    /** Turns the rank mode on or off. In rank mode the outputs of add_entry_out_of_order are
+     * ignored: inserting or removing a word shifts the ranks of all following words, so that
+     * the outputs stay the ranks. Panics when turning it on for other outputs */
+    pub fn set_rank_mode(&mut self, ranked: bool) {
+        if ranked
+            && !self
+                .entries()
+                .enumerate()
+                .all(|(rank, (_, o))| o == rank as i64)
+        {
+            panic!("The outputs must be the ranks of the words!");
+        }
+        self.ranked = ranked;
+    }
+
+    pub fn is_ranked(&self) -> bool {
+        return self.ranked;
+    }
+
+    /** Returns the rank of a word in rank mode, which a missing word gets when it is added */
+    pub fn rank_of(&self, word: &str) -> i64 {
+        if self.is_empty() {
+            return 0;
+        }
+        if let Some(rank) = self.get(word) {
+            return rank;
+        }
+        return self.rank_of_absent_word(&word.chars().collect::<Vec<char>>());
+    }
+
+    /** Adds a word with its rank and increments the ranks of the following words.
+     * An existing word keeps its rank */
+    pub(super) fn add_ranked_entry(&mut self, word: &str) {
+        if word.is_empty() {
+            panic!("The transducer cannot take epsilon as input!");
+        }
+
+        if self.is_empty() {
+            self.reset(Transducer::from_entry(word, 0));
+            self.reduce_to_epsilon();
+        } else if self.get(word).is_none() {
+            let word_vec: Vec<char> = word.chars().collect();
+            let rank = self.rank_of_absent_word(&word_vec);
+
+            let word_lcp = self.longest_common_prefix(&word_vec);
+            self.increase_except_from_epsilon_to_word(&word_lcp);
+            self.add_entry_in_order(word, rank);
+            self.shift_ranks_after(&word_vec, true);
+            self.reduce_to_epsilon();
+        }
+
+        self.compact_if_fragmented();
+
+        #[cfg(feature = "check-invariants")]
+        self.assert_invariants();
+    }
+
+    /** Increments or decrements the outputs of all words after a word, whose states must be on
+     * min_except. The outputs are the ranks in canonical form, so the output of a transition is
+     * the number of words of its source state before it. Only the transitions after the path of
+     * the word lead to words after it, and the word itself is the smallest word of the states
+     * after them, so the minimal outputs of the states stay 0 */
+    pub(super) fn shift_ranks_after(&mut self, word: &[char], increase: bool) {
+        let path = self.state_sequence(word);
+
+        for (i, q) in path.into_iter().enumerate() {
+            let next_char = word.get(i);
+
+            for t in self.state_mut(q).transitions.iter_mut() {
+                if next_char.is_some_and(|ch| t.ch <= *ch) {
+                    continue;
+                }
+
+                t.output = sum(t.output, if increase { 1 } else { -1 });
+            }
+        }
+    }
+
+    /** Returns the number of words before a word that is not in the transducer */
+    fn rank_of_absent_word(&self, word: &[char]) -> i64 {
+        let mut rank = self.iota;
+        let mut q = self.init_state;
+
+        for ch in word {
+            let state = self.state(q);
+
+            match state.transition(*ch) {
+                Some(t) => {
+                    rank = sum(rank, t.output);
+                    q = t.to;
+                }
+                None => {
+                    // The words after the missing transition are all after the word
+                    return sum(
+                        rank,
+                        match state.transitions.iter().find(|t| t.ch > *ch) {
+                            Some(t) => t.output,
+                            None => self.count_words_from(q),
+                        },
+                    );
+                }
+            }
+        }
+
+        // The word is a prefix of all words through the state, which are after it
+        return rank;
+    }
+
+    /** Returns the number of words from a state, by following its last transitions, whose
+     * outputs are the number of words before them */
+    fn count_words_from(&self, q: usize) -> i64 {
+        let mut count = 0;
+        let mut q = q;
+
+        while let Some(t) = self.state(q).transitions.last() {
+            count = sum(count, t.output);
+            q = t.to;
+        }
+
+        return sum(count, 1);
+    }
+}
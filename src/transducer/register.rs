@@ -0,0 +1,81 @@
+use std::{collections::HashMap, mem::size_of};
+
+use super::memory::hash_table_bytes;
+
+/** The register of the states outside of min_except, hash-consed by their signature.
+ * Only the hash of a signature is stored, so states with colliding hashes share a bucket
+ * and are told apart by the given equivalence check on their transitions */
+#[derive(Debug, Clone, Default)]
+pub(super) struct Register {
+    buckets: HashMap<u64, Vec<usize>>,
+}
+
+impl Register {
+    pub(super) fn with_capacity(capacity: usize) -> Self {
+        return Self {
+            buckets: HashMap::with_capacity(capacity),
+        };
+    }
+
+    /** Returns the registered state of a bucket that is equivalent according to the check */
+    pub(super) fn find<F>(&self, hash: u64, mut is_equivalent: F) -> Option<usize>
+    where
+        F: FnMut(usize) -> bool,
+    {
+        return self
+            .buckets
+            .get(&hash)?
+            .iter()
+            .copied()
+            .find(|q| is_equivalent(*q));
+    }
+
+    /** Registers a state, replacing an equivalent state that is already registered */
+    pub(super) fn insert<F>(&mut self, hash: u64, q: usize, mut is_equivalent: F)
+    where
+        F: FnMut(usize) -> bool,
+    {
+        let bucket = self.buckets.entry(hash).or_default();
+
+        match bucket
+            .iter()
+            .position(|q_eq| *q_eq == q || is_equivalent(*q_eq))
+        {
+            Some(i) => bucket[i] = q,
+            None => bucket.push(q),
+        }
+    }
+
+    /** Unregisters the state that is equivalent according to the check and returns it */
+    pub(super) fn remove<F>(&mut self, hash: u64, is_equivalent: F) -> Option<usize>
+    where
+        F: FnMut(usize) -> bool,
+    {
+        let bucket = self.buckets.get_mut(&hash)?;
+        let i = bucket.iter().copied().position(is_equivalent)?;
+        let q = bucket.swap_remove(i);
+
+        if bucket.is_empty() {
+            self.buckets.remove(&hash);
+        }
+        return Some(q);
+    }
+
+    /** Iterates over the registered states with the hashes they are registered under */
+    pub(super) fn iter(&self) -> impl Iterator<Item = (u64, usize)> + '_ {
+        return self
+            .buckets
+            .iter()
+            .flat_map(|(hash, bucket)| bucket.iter().map(move |q| (*hash, *q)));
+    }
+
+    /** Estimates the bytes used by the buckets and the hash table */
+    pub(super) fn memory_usage(&self) -> usize {
+        let buckets: usize = self
+            .buckets
+            .values()
+            .map(|bucket| bucket.capacity() * size_of::<usize>())
+            .sum();
+        return hash_table_bytes::<(u64, Vec<usize>)>(self.buckets.capacity()) + buckets;
+    }
+}
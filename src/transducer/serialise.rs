@@ -0,0 +1,187 @@
+use std::io::{self, Read, Write};
+
+use super::Transducer;
+
+const MAGIC: &[u8; 4] = b"MSTX";
+const VERSION: u32 = 3;
+/** The version before the flags of the header, which is read like the current one otherwise */
+const UNFLAGGED_VERSION: u32 = 2;
+/** The version before signed outputs, whose outputs are read the same way when they are below
+ * 2^63 */
+const UNSIGNED_VERSION: u32 = 1;
+/** The flag of the header for the rank mode. The older versions have no flags */
+const RANKED: u32 = 1;
+
+impl Transducer {
+    /** Writes the transducer (minimal except in epsilon) in a compact binary format.
+     * States are renumbered densely, so the saved form does not depend on the state ids */
+    pub fn save<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = io::BufWriter::new(writer);
+        let numbered = self.numbered();
+
+        writer.write_all(MAGIC)?;
+        write_u32(&mut writer, VERSION)?;
+        write_u32(&mut writer, if self.ranked { RANKED } else { 0 })?;
+        write_u64(&mut writer, numbered.num_states)?;
+        write_i64(&mut writer, self.iota)?;
+
+        write_u64(&mut writer, numbered.finals.len())?;
+        for (q, o) in numbered.finals {
+            write_u64(&mut writer, q)?;
+            write_i64(&mut writer, o)?;
+        }
+
+        write_u64(&mut writer, numbered.transitions.len())?;
+        for (q1, a, q2, o) in numbered.transitions {
+            write_u64(&mut writer, q1)?;
+            write_u32(&mut writer, a as u32)?;
+            write_u64(&mut writer, q2)?;
+            write_i64(&mut writer, o)?;
+        }
+
+        return writer.flush();
+    }
+
+    /** The number of bytes that save writes, without writing them. All live states are
+     * reachable, so every final state and every transition is saved */
+    pub(super) fn serialised_size(&self) -> usize {
+        let header = MAGIC.len() + 4 + 4 + 8 + 8;
+        let finals = 8 + self.get_number_of_final_states() * (8 + 8);
+        let transitions = 8 + self.get_number_of_transitions() * (8 + 4 + 8 + 8);
+        return header + finals + transitions;
+    }
+
+    /** Reads a transducer written by save */
+    pub fn load<R: Read>(reader: R) -> io::Result<Self> {
+        let mut reader = io::BufReader::new(reader);
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a saved transducer"));
+        }
+
+        let version = read_u32(&mut reader)?;
+        if ![VERSION, UNFLAGGED_VERSION, UNSIGNED_VERSION].contains(&version) {
+            return Err(invalid_data(&format!("unsupported version {}", version)));
+        }
+        let flags = match version {
+            VERSION => read_u32(&mut reader)?,
+            _ => 0,
+        };
+
+        let num_states = read_u64(&mut reader)?;
+        if num_states == 0 {
+            return Err(invalid_data("a transducer has at least an initial state"));
+        }
+        let read_output = |reader: &mut io::BufReader<R>| {
+            let output = read_i64(reader)?;
+            if version == UNSIGNED_VERSION && output < 0 {
+                return Err(invalid_data("output does not fit in i64"));
+            }
+            return Ok(output);
+        };
+
+        let iota = read_output(&mut reader)?;
+        let check_state = |q: usize| {
+            if q < num_states {
+                return Ok(q);
+            }
+            return Err(invalid_data(&format!("state {} is out of range", q)));
+        };
+
+        // The counts are not trusted for allocations, only the entries that are actually read
+        let num_finals = read_u64(&mut reader)?;
+        let mut finals = Vec::new();
+        for _ in 0..num_finals {
+            let q = check_state(read_u64(&mut reader)?)?;
+            finals.push((q, read_output(&mut reader)?));
+        }
+
+        let num_transitions = read_u64(&mut reader)?;
+        let mut transitions = Vec::new();
+        for _ in 0..num_transitions {
+            let q1 = check_state(read_u64(&mut reader)?)?;
+            let a = char::from_u32(read_u32(&mut reader)?)
+                .ok_or_else(|| invalid_data("invalid character"))?;
+            let q2 = check_state(read_u64(&mut reader)?)?;
+            transitions.push((q1, a, q2, read_output(&mut reader)?));
+        }
+
+        // Every state but the initial one is reached by a transition, which bounds the states
+        if num_states - 1 > transitions.len() {
+            return Err(invalid_data("some states are not reachable"));
+        }
+        if has_cycle(num_states, &transitions) {
+            return Err(invalid_data("the transitions contain a cycle"));
+        }
+
+        let mut transducer = Transducer::from_parts(num_states, transitions, finals, iota);
+        transducer.ranked = flags & RANKED != 0;
+
+        if let Err(violations) = transducer.validate() {
+            return Err(invalid_data(&violations[0].to_string()));
+        }
+        return Ok(transducer);
+    }
+}
+
+/** Checks if the transitions between the states 0..n contain a cycle, by removing the states
+ * without ingoing transitions until none are left */
+fn has_cycle(n: usize, transitions: &[(usize, char, usize, i64)]) -> bool {
+    let mut successors = vec![Vec::new(); n];
+    let mut in_degrees = vec![0; n];
+    for (q1, _, q2, _) in transitions {
+        successors[*q1].push(*q2);
+        in_degrees[*q2] += 1;
+    }
+
+    let mut sources: Vec<usize> = (0..n).filter(|q| in_degrees[*q] == 0).collect();
+    let mut removed = 0;
+    while let Some(q) = sources.pop() {
+        removed += 1;
+        for next in &successors[q] {
+            in_degrees[*next] -= 1;
+            if in_degrees[*next] == 0 {
+                sources.push(*next);
+            }
+        }
+    }
+
+    return removed < n;
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message);
+}
+
+fn write_u32<W: Write>(writer: &mut W, n: u32) -> io::Result<()> {
+    return writer.write_all(&n.to_le_bytes());
+}
+
+fn write_u64<W: Write>(writer: &mut W, n: usize) -> io::Result<()> {
+    return writer.write_all(&(n as u64).to_le_bytes());
+}
+
+fn write_i64<W: Write>(writer: &mut W, n: i64) -> io::Result<()> {
+    return writer.write_all(&n.to_le_bytes());
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    return Ok(u32::from_le_bytes(bytes));
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    return usize::try_from(u64::from_le_bytes(bytes))
+        .map_err(|_| invalid_data("value does not fit in usize"));
+}
+
+fn read_i64<R: Read>(reader: &mut R) -> io::Result<i64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    return Ok(i64::from_le_bytes(bytes));
+}
@@ -0,0 +1,83 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/** A transition of a state, with its output stored inline */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(super) struct Transition {
+    pub(super) ch: char,
+    pub(super) to: usize,
+    pub(super) output: i64,
+}
+
+/** A state of the arena. The transitions are sorted by their character,
+ * so that a transition step is a binary search in a small vector */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct State {
+    pub(super) transitions: Vec<Transition>,
+    /** The (char, state) pairs of the ingoing transitions */
+    pub(super) predecessors: Vec<(char, usize)>,
+    /** The final output, if the state is final */
+    pub(super) final_output: Option<i64>,
+}
+
+impl State {
+    pub(super) fn is_final(&self) -> bool {
+        return self.final_output.is_some();
+    }
+
+    pub(super) fn transition(&self, ch: char) -> Option<&Transition> {
+        let i = self.transitions.binary_search_by_key(&ch, |t| t.ch).ok()?;
+        return Some(&self.transitions[i]);
+    }
+
+    pub(super) fn transition_mut(&mut self, ch: char) -> Option<&mut Transition> {
+        let i = self.transitions.binary_search_by_key(&ch, |t| t.ch).ok()?;
+        return Some(&mut self.transitions[i]);
+    }
+
+    /** Returns the state reached with the given character */
+    pub(super) fn next(&self, ch: char) -> Option<usize> {
+        return self.transition(ch).map(|t| t.to);
+    }
+
+    /** Adds a transition, overwriting the one with the same character.
+     * Returns the overwritten transition */
+    pub(super) fn set_transition(&mut self, transition: Transition) -> Option<Transition> {
+        match self
+            .transitions
+            .binary_search_by_key(&transition.ch, |t| t.ch)
+        {
+            Ok(i) => return Some(std::mem::replace(&mut self.transitions[i], transition)),
+            Err(i) => {
+                self.transitions.insert(i, transition);
+                return None;
+            }
+        }
+    }
+
+    pub(super) fn remove_transition(&mut self, ch: char) -> Option<Transition> {
+        let i = self.transitions.binary_search_by_key(&ch, |t| t.ch).ok()?;
+        return Some(self.transitions.remove(i));
+    }
+
+    pub(super) fn remove_predecessor(&mut self, ch: char, q: usize) {
+        if let Some(i) = self.predecessors.iter().rposition(|p| *p == (ch, q)) {
+            self.predecessors.swap_remove(i);
+        }
+    }
+
+    /** Hashes the final output and the transitions, which make up the signature of the state */
+    pub(super) fn signature_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.final_output.hash(&mut hasher);
+        self.transitions.hash(&mut hasher);
+        return hasher.finish();
+    }
+
+    /** Checks if two states have the same final output and the same transitions */
+    pub(super) fn has_same_signature(&self, other: &State) -> bool {
+        return self.final_output == other.final_output && self.transitions == other.transitions;
+    }
+}
@@ -2,11 +2,16 @@
 mod tests {
     use std::{
         collections::{BTreeSet, HashMap, HashSet},
+        mem::size_of,
         vec,
     };
 
     use crate::transducer::{
-        utils::{add_to_or_insert, longest_common_prefix},
+        register::Register,
+        state::{State, Transition},
+        utils::longest_common_prefix,
+        BuildError, CompositionError, ConflictPolicy, DiffEntry, DuplicatePolicy,
+        InvariantViolation, InversionError, KeyEncoding, OverflowError, StringTransducer,
         Transducer,
     };
 
@@ -15,11 +20,11 @@ mod tests {
         let transducer = Transducer::from_entry("baba", 10);
 
         assert_eq!(transducer.alphabet, HashSet::from(['a', 'b']));
-        assert_eq!(transducer.states, BTreeSet::from([0, 1, 2, 3, 4]));
-        assert_eq!(transducer.finality, BTreeSet::from([4]));
+        assert_eq!(transducer.state_set(), BTreeSet::from([0, 1, 2, 3, 4]));
+        assert_eq!(transducer.finality(), BTreeSet::from([4]));
         assert_eq!(transducer.init_state, 0);
         assert_eq!(
-            transducer.delta,
+            transducer.delta(),
             HashMap::from([
                 (0, HashMap::from([('b', 1)])),
                 (1, HashMap::from([('a', 2)])),
@@ -28,7 +33,7 @@ mod tests {
             ])
         );
         assert_eq!(
-            transducer.delta_inv,
+            transducer.delta_inv(),
             HashMap::from([
                 (4, HashSet::from([('a', 3)])),
                 (3, HashSet::from([('b', 2)])),
@@ -37,7 +42,7 @@ mod tests {
             ])
         );
         assert_eq!(
-            transducer.lambda,
+            transducer.lambda(),
             HashMap::from([
                 (0, HashMap::from([('b', 0)])),
                 (1, HashMap::from([('a', 0)])),
@@ -46,9 +51,9 @@ mod tests {
             ])
         );
         assert_eq!(transducer.iota, 10);
-        assert_eq!(transducer.psi, HashMap::from([(4, 0)]));
+        assert_eq!(transducer.psi(), HashMap::from([(4, 0)]));
         assert_eq!(transducer.min_except, vec!['b', 'a', 'b', 'a']);
-        assert_eq!(transducer.states_by_signature, HashMap::new());
+        assert_eq!(transducer.states_by_signature(), HashMap::new());
     }
 
     #[test]
@@ -60,17 +65,17 @@ mod tests {
         let expected_transducer = example_transducer();
 
         assert_eq!(transducer.alphabet, expected_transducer.alphabet);
-        assert_eq!(transducer.states, expected_transducer.states);
-        assert_eq!(transducer.finality, BTreeSet::from([2, 3, 5]));
+        assert_eq!(transducer.state_set(), expected_transducer.state_set());
+        assert_eq!(transducer.finality(), BTreeSet::from([2, 3, 5]));
         assert_eq!(transducer.init_state, expected_transducer.init_state);
-        assert_eq!(transducer.delta, expected_transducer.delta);
-        assert_eq!(transducer.delta_inv, expected_transducer.delta_inv);
-        assert_eq!(transducer.lambda, expected_transducer.lambda);
+        assert_eq!(transducer.delta(), expected_transducer.delta());
+        assert_eq!(transducer.delta_inv(), expected_transducer.delta_inv());
+        assert_eq!(transducer.lambda(), expected_transducer.lambda());
         assert_eq!(transducer.iota, expected_transducer.iota);
-        assert_eq!(transducer.psi, HashMap::from([(2, 1), (3, 5), (5, 0)]));
+        assert_eq!(transducer.psi(), HashMap::from([(2, 1), (3, 5), (5, 0)]));
         assert_eq!(transducer.min_except, expected_transducer.min_except);
         assert_eq!(
-            transducer.states_by_signature,
+            transducer.states_by_signature(),
             HashMap::from([
                 ((None, BTreeSet::from([('c', 1, 0)])), 0),
                 ((None, BTreeSet::from([('a', 2, 5), ('b', 6, 0)])), 1),
@@ -127,16 +132,16 @@ mod tests {
         ]);
 
         assert_eq!(transducer.alphabet, alphabet);
-        assert_eq!(transducer.states, states);
-        assert_eq!(transducer.finality, finality);
+        assert_eq!(transducer.state_set(), states);
+        assert_eq!(transducer.finality(), finality);
         assert_eq!(transducer.init_state, init_state);
-        assert_eq!(transducer.delta, delta);
-        assert_eq!(transducer.delta_inv, delta_inv);
-        assert_eq!(transducer.lambda, lambda);
+        assert_eq!(transducer.delta(), delta);
+        assert_eq!(transducer.delta_inv(), delta_inv);
+        assert_eq!(transducer.lambda(), lambda);
         assert_eq!(transducer.iota, iota);
-        assert_eq!(transducer.psi, psi);
+        assert_eq!(transducer.psi(), psi);
         assert_eq!(transducer.min_except, min_except);
-        assert_eq!(transducer.states_by_signature, states_by_signature);
+        assert_eq!(transducer.states_by_signature(), states_by_signature);
     }
 
     #[test]
@@ -179,16 +184,16 @@ mod tests {
         ]);
 
         assert_eq!(transducer.alphabet, alphabet);
-        assert_eq!(transducer.states, states);
-        assert_eq!(transducer.finality, finality);
+        assert_eq!(transducer.state_set(), states);
+        assert_eq!(transducer.finality(), finality);
         assert_eq!(transducer.init_state, init_state);
-        assert_eq!(transducer.delta, delta);
-        assert_eq!(transducer.delta_inv, delta_inv);
-        assert_eq!(transducer.lambda, lambda);
+        assert_eq!(transducer.delta(), delta);
+        assert_eq!(transducer.delta_inv(), delta_inv);
+        assert_eq!(transducer.lambda(), lambda);
         assert_eq!(transducer.iota, iota);
-        assert_eq!(transducer.psi, psi);
+        assert_eq!(transducer.psi(), psi);
         assert_eq!(transducer.min_except, min_except);
-        assert_eq!(transducer.states_by_signature, states_by_signature);
+        assert_eq!(transducer.states_by_signature(), states_by_signature);
     }
 
     #[test]
@@ -231,16 +236,16 @@ mod tests {
         ]);
 
         assert_eq!(transducer.alphabet, alphabet);
-        assert_eq!(transducer.states, states);
-        assert_eq!(transducer.finality, finality);
+        assert_eq!(transducer.state_set(), states);
+        assert_eq!(transducer.finality(), finality);
         assert_eq!(transducer.init_state, init_state);
-        assert_eq!(transducer.delta, delta);
-        assert_eq!(transducer.delta_inv, delta_inv);
-        assert_eq!(transducer.lambda, lambda);
+        assert_eq!(transducer.delta(), delta);
+        assert_eq!(transducer.delta_inv(), delta_inv);
+        assert_eq!(transducer.lambda(), lambda);
         assert_eq!(transducer.iota, iota);
-        assert_eq!(transducer.psi, psi);
+        assert_eq!(transducer.psi(), psi);
         assert_eq!(transducer.min_except, min_except);
-        assert_eq!(transducer.states_by_signature, states_by_signature);
+        assert_eq!(transducer.states_by_signature(), states_by_signature);
     }
 
     #[test]
@@ -249,11 +254,11 @@ mod tests {
         transducer.add_entry_in_order("bc", 15);
 
         assert_eq!(transducer.alphabet, HashSet::from(['a', 'b', 'c']));
-        assert_eq!(transducer.states, BTreeSet::from([0, 1, 2, 3, 4, 5]));
-        assert_eq!(transducer.finality, BTreeSet::from([4, 5]));
+        assert_eq!(transducer.state_set(), BTreeSet::from([0, 1, 2, 3, 4, 5]));
+        assert_eq!(transducer.finality(), BTreeSet::from([4, 5]));
         assert_eq!(transducer.init_state, 0);
         assert_eq!(
-            transducer.delta,
+            transducer.delta(),
             HashMap::from([
                 (0, HashMap::from([('b', 1)])),
                 (1, HashMap::from([('a', 2), ('c', 5)])),
@@ -262,7 +267,7 @@ mod tests {
             ])
         );
         assert_eq!(
-            transducer.delta_inv,
+            transducer.delta_inv(),
             HashMap::from([
                 (5, HashSet::from([('c', 1)])),
                 (4, HashSet::from([('a', 3)])),
@@ -272,7 +277,7 @@ mod tests {
             ])
         );
         assert_eq!(
-            transducer.lambda,
+            transducer.lambda(),
             HashMap::from([
                 (0, HashMap::from([('b', 0)])),
                 (1, HashMap::from([('a', 0), ('c', 5)])),
@@ -281,10 +286,10 @@ mod tests {
             ])
         );
         assert_eq!(transducer.iota, 10);
-        assert_eq!(transducer.psi, HashMap::from([(4, 0), (5, 0)]));
+        assert_eq!(transducer.psi(), HashMap::from([(4, 0), (5, 0)]));
         assert_eq!(transducer.min_except, vec!['b', 'c']);
         assert_eq!(
-            transducer.states_by_signature,
+            transducer.states_by_signature(),
             HashMap::from([
                 ((None, BTreeSet::from([('b', 3, 0)])), 2),
                 ((None, BTreeSet::from([('a', 4, 0)])), 3),
@@ -300,18 +305,18 @@ mod tests {
         let expected_transducer = example_transducer();
 
         assert_eq!(transducer.alphabet, expected_transducer.alphabet);
-        assert_eq!(transducer.states, expected_transducer.states);
-        assert_eq!(transducer.finality, expected_transducer.finality);
+        assert_eq!(transducer.state_set(), expected_transducer.state_set());
+        assert_eq!(transducer.finality(), expected_transducer.finality());
         assert_eq!(transducer.init_state, expected_transducer.init_state);
-        assert_eq!(transducer.delta, expected_transducer.delta);
-        assert_eq!(transducer.delta_inv, expected_transducer.delta_inv);
-        assert_eq!(transducer.lambda, expected_transducer.lambda);
+        assert_eq!(transducer.delta(), expected_transducer.delta());
+        assert_eq!(transducer.delta_inv(), expected_transducer.delta_inv());
+        assert_eq!(transducer.lambda(), expected_transducer.lambda());
         assert_eq!(transducer.iota, expected_transducer.iota);
-        assert_eq!(transducer.psi, expected_transducer.psi);
+        assert_eq!(transducer.psi(), expected_transducer.psi());
         assert_eq!(transducer.min_except, expected_transducer.min_except);
         assert_eq!(
-            transducer.states_by_signature,
-            expected_transducer.states_by_signature
+            transducer.states_by_signature(),
+            expected_transducer.states_by_signature()
         );
     }
 
@@ -323,16 +328,561 @@ mod tests {
         let expected = example_transducer4();
 
         assert_eq!(transducer.alphabet, expected.alphabet);
-        assert_eq!(transducer.states, expected.states);
-        assert_eq!(transducer.finality, expected.finality);
+        assert_eq!(transducer.state_set(), expected.state_set());
+        assert_eq!(transducer.finality(), expected.finality());
         assert_eq!(transducer.init_state, expected.init_state);
-        assert_eq!(transducer.delta, expected.delta);
-        assert_eq!(transducer.delta_inv, expected.delta_inv);
-        assert_eq!(transducer.lambda, expected.lambda);
+        assert_eq!(transducer.delta(), expected.delta());
+        assert_eq!(transducer.delta_inv(), expected.delta_inv());
+        assert_eq!(transducer.lambda(), expected.lambda());
         assert_eq!(transducer.iota, expected.iota);
-        assert_eq!(transducer.psi, expected.psi);
+        assert_eq!(transducer.psi(), expected.psi());
         assert_eq!(transducer.min_except, expected.min_except);
-        assert_eq!(transducer.states_by_signature, expected.states_by_signature);
+        assert_eq!(
+            transducer.states_by_signature(),
+            expected.states_by_signature()
+        );
+    }
+
+    #[test]
+    fn constructs_the_transducer_from_a_sorted_iterator() {
+        let dictionary = vec![
+            (String::from("cab"), 15),
+            (String::from("cabab"), 10),
+            (String::from("cad"), 8),
+            (String::from("cbab"), 3),
+        ];
+        let transducer = Transducer::from_sorted_iter(dictionary);
+        let expected_transducer = example_transducer();
+
+        assert_eq!(transducer.state_set(), expected_transducer.state_set());
+        assert_eq!(transducer.finality(), expected_transducer.finality());
+        assert_eq!(transducer.delta(), expected_transducer.delta());
+        assert_eq!(transducer.lambda(), expected_transducer.lambda());
+        assert_eq!(transducer.iota, expected_transducer.iota);
+        assert_eq!(transducer.psi(), expected_transducer.psi());
+        assert_eq!(
+            transducer.states_by_signature(),
+            expected_transducer.states_by_signature()
+        );
+    }
+
+    #[test]
+    fn collects_the_transducer_from_an_iterator() {
+        let words = ["cab", "cabab", "cad", "cbab"];
+        let outputs = [15, 10, 8, 3];
+        let transducer: Transducer = words.iter().zip(outputs).collect();
+        let expected_transducer = example_transducer();
+
+        assert_eq!(transducer.state_set(), expected_transducer.state_set());
+        assert_eq!(transducer.delta(), expected_transducer.delta());
+        assert_eq!(transducer.lambda(), expected_transducer.lambda());
+        assert_eq!(transducer.iota, expected_transducer.iota);
+        assert_eq!(transducer.psi(), expected_transducer.psi());
+    }
+
+    #[test]
+    fn extends_the_transducer_with_unsorted_entries() {
+        let mut transducer = Transducer::from_dictionary(vec![("cab", 15), ("cbab", 3)]);
+        transducer.extend(vec![("cad", 8), ("cabab", 10)]);
+        let expected_transducer = example_transducer();
+
+        assert_eq!(
+            transducer.state_set().len(),
+            expected_transducer.state_set().len()
+        );
+        assert_eq!(transducer.iota, expected_transducer.iota);
+        for (w, o) in [("cab", 15), ("cabab", 10), ("cad", 8), ("cbab", 3)] {
+            assert_eq!(transducer.output(&w.chars().collect()), o);
+        }
+    }
+
+    #[test]
+    fn inserts_a_batch_of_unsorted_entries() {
+        let mut transducer = Transducer::from_dictionary(vec![("cab", 1), ("cbab", 3)]);
+        transducer.insert_batch(vec![("cad", 8), ("cabab", 10), ("cab", 2), ("cab", 15)]);
+        let expected_transducer = example_transducer();
+
+        assert_eq!(
+            transducer.entries().collect::<Vec<_>>(),
+            expected_transducer.entries().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            transducer.state_set().len(),
+            expected_transducer.state_set().len()
+        );
+        assert_eq!(transducer.validate(), Ok(()));
+
+        let mut transducer = Transducer::from_dictionary(vec![("x", 1)]);
+        transducer.remove_entry_with_word("x");
+        transducer.insert_batch(vec![("cbab", 3), ("cad", 8), ("cabab", 10), ("cab", 15)]);
+        assert_eq!(
+            transducer.state_set().len(),
+            expected_transducer.state_set().len()
+        );
+        assert_eq!(transducer.validate(), Ok(()));
+    }
+
+    #[test]
+    fn removes_a_batch_of_unsorted_words() {
+        let mut transducer = example_transducer();
+        transducer.add_entry_out_of_order("ca", 9);
+
+        let removed = transducer.remove_batch(["ca", "cbab", "x", "cab", "ca"]);
+
+        assert_eq!(
+            removed,
+            vec![
+                (String::from("ca"), 9),
+                (String::from("cab"), 15),
+                (String::from("cbab"), 3),
+            ]
+        );
+        assert_eq!(
+            transducer.entries().collect::<Vec<_>>(),
+            vec![(String::from("cabab"), 10), (String::from("cad"), 8)]
+        );
+        assert_eq!(transducer.validate(), Ok(()));
+        assert!(transducer.is_minimal());
+
+        assert_eq!(transducer.remove_batch(["cad", "cabab"]).len(), 2);
+        assert!(transducer.is_empty());
+        assert_eq!(transducer.validate(), Ok(()));
+    }
+
+    #[test]
+    fn constructs_from_an_empty_iterator() {
+        let mut transducer = Transducer::from_sorted_iter(Vec::<(&str, i64)>::new());
+        assert!(transducer.is_empty());
+        assert_eq!(transducer.entries().count(), 0);
+        assert_eq!(transducer.validate(), Ok(()));
+
+        transducer.add_entry_out_of_order("cab", 15);
+        assert_eq!(transducer.get("cab"), Some(15));
+
+        let transducer: Transducer = std::iter::empty::<(&str, i64)>().collect();
+        assert!(transducer.is_empty());
+        assert_eq!(transducer.validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_entries_out_of_order() {
+        let dictionary = vec![("cab", 15), ("cad", 8), ("cabab", 10)];
+        let result = Transducer::try_from_sorted_iter(dictionary, DuplicatePolicy::Reject);
+
+        assert_eq!(
+            result.err(),
+            Some(BuildError::OutOfOrder {
+                line: 3,
+                word: String::from("cabab"),
+                previous: String::from("cad"),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_entries() {
+        let dictionary = vec![("cab", 15), ("cabab", 10), ("cabab", 11)];
+        let result = Transducer::try_from_sorted_iter(dictionary, DuplicatePolicy::Reject);
+
+        assert_eq!(
+            result.err(),
+            Some(BuildError::Duplicate {
+                line: 3,
+                word: String::from("cabab"),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_empty_words_and_empty_input() {
+        let result = Transducer::try_from_sorted_iter(vec![("", 1)], DuplicatePolicy::Reject);
+        assert_eq!(result.err(), Some(BuildError::EmptyWord { line: 1 }));
+
+        let result =
+            Transducer::try_from_sorted_iter(Vec::<(&str, i64)>::new(), DuplicatePolicy::Reject);
+        assert_eq!(result.err(), Some(BuildError::Empty));
+    }
+
+    #[test]
+    fn resolves_duplicate_entries_with_a_policy() {
+        let dictionary = vec![
+            ("cab", 15),
+            ("cabab", 10),
+            ("cabab", 4),
+            ("cad", 8),
+            ("cbab", 3),
+        ];
+        let cabab: Vec<char> = "cabab".chars().collect();
+
+        for (policy, expected) in [
+            (DuplicatePolicy::KeepFirst, 10),
+            (DuplicatePolicy::KeepLast, 4),
+            (DuplicatePolicy::Min, 4),
+            (DuplicatePolicy::Sum, 14),
+        ] {
+            let transducer = Transducer::try_from_sorted_iter(dictionary.clone(), policy).unwrap();
+            assert_eq!(transducer.output(&cabab), expected);
+            assert_eq!(transducer.output(&"cad".chars().collect()), 8);
+        }
+
+        let transducer =
+            Transducer::try_from_sorted_iter(dictionary, DuplicatePolicy::KeepFirst).unwrap();
+        let expected_transducer = example_transducer();
+        assert_eq!(transducer.delta(), expected_transducer.delta());
+        assert_eq!(transducer.lambda(), expected_transducer.lambda());
+        assert_eq!(transducer.psi(), expected_transducer.psi());
+    }
+
+    #[test]
+    #[should_panic]
+    fn fails_to_add_entry_in_order_before_min_except() {
+        let mut transducer = Transducer::from_entry("cab", 15);
+        transducer.add_entry_in_order("ca", 9);
+    }
+
+    #[test]
+    #[should_panic(expected = "The transducer cannot take epsilon as input!")]
+    fn fails_to_add_epsilon_out_of_order() {
+        let mut transducer = example_transducer();
+        transducer.add_entry_out_of_order("", 5);
+    }
+
+    #[test]
+    fn looks_up_and_enumerates_entries() {
+        let transducer = example_transducer();
+
+        assert_eq!(transducer.get("cabab"), Some(10));
+        assert_eq!(transducer.get("caba"), None);
+        assert_eq!(transducer.get("x"), None);
+        assert_eq!(
+            transducer.entries().collect::<Vec<_>>(),
+            vec![
+                (String::from("cab"), 15),
+                (String::from("cabab"), 10),
+                (String::from("cad"), 8),
+                (String::from("cbab"), 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn enumerates_entries_with_a_prefix() {
+        let transducer = example_transducer();
+        let with_prefix = |prefix: &str| -> Vec<(String, i64)> {
+            transducer.entries_with_prefix(prefix).collect()
+        };
+
+        assert_eq!(
+            with_prefix("cab"),
+            vec![(String::from("cab"), 15), (String::from("cabab"), 10)]
+        );
+        assert_eq!(with_prefix("caba"), vec![(String::from("cabab"), 10)]);
+        assert_eq!(with_prefix("cbab"), vec![(String::from("cbab"), 3)]);
+        assert_eq!(with_prefix("cx"), vec![]);
+        assert_eq!(with_prefix(""), transducer.entries().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn renders_the_transducer_in_dot() {
+        let transducer = Transducer::from_dictionary(vec![("a\"", 3), ("b", 5)]);
+        let dot = transducer.to_dot();
+
+        assert!(dot.starts_with("digraph transducer {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains(&format!(
+            "start -> {} [label=\"3\"];",
+            transducer.init_state
+        )));
+        assert!(dot.contains("label=\"\\\"/0\""));
+        assert!(dot.contains("label=\"b/2\""));
+        assert_eq!(dot.matches("doublecircle").count(), 1);
+    }
+
+    #[test]
+    fn removes_missing_word() {
+        let mut transducer = example_transducer();
+
+        assert_eq!(transducer.remove_entry_with_word("caba"), None);
+        assert_eq!(transducer.remove_entry_with_word("cabd"), None);
+        assert_eq!(transducer.state_set(), example_transducer().state_set());
+        assert_eq!(transducer.delta(), example_transducer().delta());
+    }
+
+    #[test]
+    fn removes_the_only_word_and_adds_it_back() {
+        let mut transducer = Transducer::from_dictionary(vec![("cab", 15)]);
+
+        assert_eq!(transducer.remove_entry_with_word("cab"), Some(15));
+        assert!(transducer.is_empty());
+        assert_eq!(transducer.state_set(), BTreeSet::from([0]));
+        assert_eq!(transducer.entries().count(), 0);
+        assert_eq!(transducer.validate(), Ok(()));
+
+        transducer.add_entry_out_of_order("ca", 9);
+        assert_eq!(transducer.get("ca"), Some(9));
+        assert_eq!(transducer.validate(), Ok(()));
+    }
+
+    #[test]
+    fn saves_and_loads_the_transducer() {
+        let mut transducer = example_transducer();
+        transducer.add_entry_out_of_order("ca", 9);
+        transducer.remove_entry_with_word("cabab");
+
+        let mut bytes = Vec::new();
+        transducer.save(&mut bytes).unwrap();
+        let loaded = Transducer::load(bytes.as_slice()).unwrap();
+
+        assert_eq!(loaded.validate(), Ok(()));
+        assert_eq!(
+            loaded.state_set(),
+            BTreeSet::from_iter(0..transducer.state_set().len())
+        );
+        assert_eq!(loaded.iota, transducer.iota);
+        assert_eq!(
+            loaded.entries().collect::<Vec<_>>(),
+            transducer.entries().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn fails_to_load_invalid_data() {
+        assert!(Transducer::load(&b"MSTY"[..]).is_err());
+
+        let mut bytes = Vec::new();
+        example_transducer().save(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        assert!(Transducer::load(bytes.as_slice()).is_err());
+    }
+
+    /** Writes the current format by hand, so that it can hold anything */
+    fn saved_bytes(
+        num_states: u64,
+        finals: &[(u64, i64)],
+        transitions: &[(u64, char, u64, i64)],
+    ) -> Vec<u8> {
+        let mut bytes = b"MSTX".to_vec();
+        bytes.extend(3u32.to_le_bytes());
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend(num_states.to_le_bytes());
+        bytes.extend(0i64.to_le_bytes());
+
+        bytes.extend((finals.len() as u64).to_le_bytes());
+        for (q, o) in finals {
+            bytes.extend(q.to_le_bytes());
+            bytes.extend(o.to_le_bytes());
+        }
+
+        bytes.extend((transitions.len() as u64).to_le_bytes());
+        for (q1, a, q2, o) in transitions {
+            bytes.extend(q1.to_le_bytes());
+            bytes.extend((*a as u32).to_le_bytes());
+            bytes.extend(q2.to_le_bytes());
+            bytes.extend(o.to_le_bytes());
+        }
+        return bytes;
+    }
+
+    #[test]
+    fn fails_to_load_inconsistent_data() {
+        let valid = saved_bytes(2, &[(1, 0)], &[(0, 'a', 1, 0)]);
+        assert_eq!(
+            Transducer::load(valid.as_slice()).unwrap().get("a"),
+            Some(0)
+        );
+
+        // The counts of the header are not allocated up front
+        let huge = saved_bytes(1 << 62, &[(1, 0)], &[(0, 'a', 1, 0)]);
+        assert!(Transducer::load(huge.as_slice()).is_err());
+        let mut truncated = saved_bytes(2, &[(1, 0)], &[]);
+        let len = truncated.len();
+        truncated[len - 8..].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(Transducer::load(truncated.as_slice()).is_err());
+
+        let cyclic = saved_bytes(2, &[(1, 0)], &[(0, 'a', 1, 0), (1, 'b', 1, 0)]);
+        assert_eq!(
+            Transducer::load(cyclic.as_slice())
+                .err()
+                .unwrap()
+                .to_string(),
+            "the transitions contain a cycle"
+        );
+
+        // The outputs are not pushed towards the initial state
+        let not_canonical = saved_bytes(2, &[(1, 0)], &[(0, 'a', 1, 5)]);
+        assert!(Transducer::load(not_canonical.as_slice()).is_err());
+    }
+
+    #[test]
+    fn loads_the_older_formats() {
+        let mut bytes = Vec::new();
+        example_transducer().save(&mut bytes).unwrap();
+
+        // Version 2 stored the same bytes without the flags
+        bytes[4..8].copy_from_slice(&2u32.to_le_bytes());
+        bytes.drain(8..12);
+        let loaded = Transducer::load(bytes.as_slice()).unwrap();
+        assert!(!loaded.is_ranked());
+        assert!(loaded == example_transducer());
+
+        // Version 1 stored the same bytes as version 2 for outputs below 2^63
+        bytes[4..8].copy_from_slice(&1u32.to_le_bytes());
+        let loaded = Transducer::load(bytes.as_slice()).unwrap();
+        assert!(!loaded.is_ranked());
+        assert_eq!(
+            loaded.entries().collect::<Vec<_>>(),
+            example_transducer().entries().collect::<Vec<_>>()
+        );
+
+        bytes[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(Transducer::load(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn keeps_negative_outputs() {
+        let mut transducer = Transducer::from_dictionary(vec![
+            ("cab", -15),
+            ("cabab", 10),
+            ("cad", -8),
+            ("cbab", 0),
+        ]);
+        assert_eq!(transducer.get_initial_output(), -15);
+        assert_eq!(transducer.get("cad"), Some(-8));
+
+        transducer.add_entry_out_of_order("ca", -20);
+        transducer.add_entry_out_of_order("cabab", -30);
+        assert_eq!(transducer.get_initial_output(), -30);
+        assert_eq!(transducer.validate(), Ok(()));
+
+        assert_eq!(transducer.remove_entry_with_word("cabab"), Some(-30));
+        assert_eq!(transducer.remove_entry_with_word("ca"), Some(-20));
+        assert_eq!(transducer.get_initial_output(), -15);
+        assert_eq!(transducer.validate(), Ok(()));
+        assert_eq!(
+            transducer.entries().collect::<Vec<_>>(),
+            vec![
+                (String::from("cab"), -15),
+                (String::from("cad"), -8),
+                (String::from("cbab"), 0),
+            ]
+        );
+        assert!(transducer.is_minimal());
+
+        let mut bytes = Vec::new();
+        transducer.save(&mut bytes).unwrap();
+        let loaded = Transducer::load(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.get("cab"), Some(-15));
+        assert!(loaded == transducer);
+    }
+
+    #[test]
+    fn rejects_entries_whose_outputs_overflow() {
+        let mut transducer = Transducer::from_dictionary(vec![("a", i64::MIN), ("b", -1)]);
+
+        // The output of the transition with b would be i64::MAX + 1
+        assert_eq!(
+            transducer.try_add_entry_out_of_order("b", 0),
+            Err(OverflowError {
+                word: String::from("b"),
+                output: Some(0),
+            })
+        );
+        assert_eq!(
+            transducer.try_add_entry_out_of_order("ba", i64::MAX),
+            Err(OverflowError {
+                word: String::from("ba"),
+                output: Some(i64::MAX),
+            })
+        );
+        assert_eq!(transducer.get("b"), Some(-1));
+        assert_eq!(transducer.get("ba"), None);
+        assert_eq!(transducer.validate(), Ok(()));
+
+        // Without its old output the minimum of the root is the one of b
+        assert_eq!(
+            transducer.try_add_entry_out_of_order("a", i64::MAX - 1),
+            Ok(())
+        );
+        assert_eq!(transducer.try_add_entry_out_of_order("a", i64::MIN), Ok(()));
+
+        // b keeps the outputs after it within the range of the root
+        transducer.add_entry_out_of_order("bc", i64::MAX - 1);
+        assert_eq!(
+            transducer.try_remove_entry_with_word("b"),
+            Err(OverflowError {
+                word: String::from("b"),
+                output: None,
+            })
+        );
+        assert_eq!(transducer.get("b"), Some(-1));
+        assert_eq!(
+            transducer.try_remove_entry_with_word("a"),
+            Ok(Some(i64::MIN))
+        );
+        assert_eq!(transducer.try_remove_entry_with_word("b"), Ok(Some(-1)));
+        assert_eq!(transducer.get("bc"), Some(i64::MAX - 1));
+        assert_eq!(transducer.validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_batches_of_removals_that_overflow() {
+        let dictionary = vec![("a", i64::MIN), ("b", -1), ("ba", 0), ("bc", i64::MAX - 1)];
+        let mut transducer = Transducer::from_dictionary(dictionary.clone());
+
+        // "ba" is removed before "b" fails, and is added back
+        assert_eq!(
+            transducer.try_remove_batch(["ba", "b"]),
+            Err(OverflowError {
+                word: String::from("b"),
+                output: None,
+            })
+        );
+        assert_eq!(
+            transducer.entries().collect::<Vec<_>>(),
+            dictionary
+                .iter()
+                .map(|(w, o)| (w.to_string(), *o))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(transducer.validate(), Ok(()));
+        assert!(transducer.is_minimal());
+
+        // Without "a" the outputs after "b" are within range
+        assert_eq!(
+            transducer.try_remove_batch(["b", "a"]),
+            Ok(vec![(String::from("a"), i64::MIN), (String::from("b"), -1)])
+        );
+        assert_eq!(transducer.get("bc"), Some(i64::MAX - 1));
+        assert_eq!(transducer.validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_sorted_entries_whose_outputs_overflow() {
+        let result = Transducer::try_from_sorted_iter(
+            vec![("a", i64::MIN), ("b", -1), ("c", 0)],
+            DuplicatePolicy::Reject,
+        );
+        assert_eq!(
+            result.err(),
+            Some(BuildError::Overflow {
+                line: 3,
+                word: String::from("c"),
+            })
+        );
+
+        let result =
+            Transducer::try_from_sorted_iter(vec![("a", i64::MAX), ("a", 1)], DuplicatePolicy::Sum);
+        assert_eq!(
+            result.err(),
+            Some(BuildError::Overflow {
+                line: 2,
+                word: String::from("a"),
+            })
+        );
+
+        let transducer = Transducer::from_sorted_iter(vec![("a", -1), ("b", i64::MAX - 1)]);
+        assert_eq!(transducer.get_initial_output(), -1);
+        assert_eq!(transducer.get("b"), Some(i64::MAX - 1));
     }
 
     #[test]
@@ -376,16 +926,16 @@ mod tests {
         ]);
 
         assert_eq!(transducer.alphabet, expected.alphabet);
-        assert_eq!(transducer.states, expected.states);
-        assert_eq!(transducer.finality, expected.finality);
+        assert_eq!(transducer.state_set(), expected.state_set());
+        assert_eq!(transducer.finality(), expected.finality());
         assert_eq!(transducer.init_state, expected.init_state);
-        assert_eq!(transducer.delta, delta);
-        assert_eq!(transducer.delta_inv, delta_inv);
-        assert_eq!(transducer.lambda, lambda);
+        assert_eq!(transducer.delta(), delta);
+        assert_eq!(transducer.delta_inv(), delta_inv);
+        assert_eq!(transducer.lambda(), lambda);
         assert_eq!(transducer.iota, iota);
-        assert_eq!(transducer.psi, psi);
+        assert_eq!(transducer.psi(), psi);
         assert_eq!(transducer.min_except, expected.min_except);
-        assert_eq!(transducer.states_by_signature, states_by_signature);
+        assert_eq!(transducer.states_by_signature(), states_by_signature);
     }
 
     #[test]
@@ -405,16 +955,19 @@ mod tests {
         let psi = HashMap::from([(3, 5), (5, 0)]);
 
         assert_eq!(transducer.alphabet, example.alphabet);
-        assert_eq!(transducer.states, example.states);
-        assert_eq!(transducer.finality, example.finality);
+        assert_eq!(transducer.state_set(), example.state_set());
+        assert_eq!(transducer.finality(), example.finality());
         assert_eq!(transducer.init_state, example.init_state);
-        assert_eq!(transducer.delta, example.delta);
-        assert_eq!(transducer.delta_inv, example.delta_inv);
-        assert_eq!(transducer.lambda, lambda);
+        assert_eq!(transducer.delta(), example.delta());
+        assert_eq!(transducer.delta_inv(), example.delta_inv());
+        assert_eq!(transducer.lambda(), lambda);
         assert_eq!(transducer.iota, iota);
-        assert_eq!(transducer.psi, psi);
+        assert_eq!(transducer.psi(), psi);
         assert_eq!(transducer.min_except, example.min_except);
-        assert_eq!(transducer.states_by_signature, example.states_by_signature);
+        assert_eq!(
+            transducer.states_by_signature(),
+            example.states_by_signature()
+        );
     }
 
     #[test]
@@ -425,13 +978,13 @@ mod tests {
 
         assert_eq!(transducer.alphabet, HashSet::from(['a', 'b', 'c', 'd']));
         assert_eq!(
-            transducer.states,
+            transducer.state_set(),
             BTreeSet::from([0, 1, 2, 3, 4, 5, 6, 7, 8])
         );
-        assert_eq!(transducer.finality, BTreeSet::from([3, 5]));
+        assert_eq!(transducer.finality(), BTreeSet::from([3, 5]));
         assert_eq!(transducer.init_state, 0);
         assert_eq!(
-            transducer.delta,
+            transducer.delta(),
             HashMap::from([
                 (0, HashMap::from([('c', 1)])),
                 (1, HashMap::from([('b', 6), ('a', 2)])),
@@ -444,7 +997,7 @@ mod tests {
             ])
         );
         assert_eq!(
-            transducer.delta_inv,
+            transducer.delta_inv(),
             HashMap::from([
                 (1, HashSet::from([('c', 0)])),
                 (2, HashSet::from([('a', 1)])),
@@ -457,7 +1010,7 @@ mod tests {
             ])
         );
         assert_eq!(
-            transducer.lambda,
+            transducer.lambda(),
             HashMap::from([
                 (0, HashMap::from([('c', 0)])),
                 (1, HashMap::from([('b', 0), ('a', 3)])),
@@ -470,10 +1023,10 @@ mod tests {
             ])
         );
         assert_eq!(transducer.iota, 3);
-        assert_eq!(transducer.psi, HashMap::from([(3, 9), (5, 0)]));
+        assert_eq!(transducer.psi(), HashMap::from([(3, 9), (5, 0)]));
         assert_eq!(transducer.min_except, Vec::new());
         assert_eq!(
-            transducer.states_by_signature,
+            transducer.states_by_signature(),
             HashMap::from([
                 ((None, BTreeSet::from([('c', 1, 0)])), 0),
                 ((None, BTreeSet::from([('a', 2, 3), ('b', 6, 0)])), 1),
@@ -577,16 +1130,16 @@ mod tests {
         ]);
 
         assert_eq!(transducer.alphabet, alphabet);
-        assert_eq!(transducer.states, states);
-        assert_eq!(transducer.finality, finality);
+        assert_eq!(transducer.state_set(), states);
+        assert_eq!(transducer.finality(), finality);
         assert_eq!(transducer.init_state, init_state);
-        assert_eq!(transducer.delta, delta);
-        assert_eq!(transducer.delta_inv, delta_inv);
-        assert_eq!(transducer.lambda, lambda);
+        assert_eq!(transducer.delta(), delta);
+        assert_eq!(transducer.delta_inv(), delta_inv);
+        assert_eq!(transducer.lambda(), lambda);
         assert_eq!(transducer.iota, iota);
-        assert_eq!(transducer.psi, psi);
+        assert_eq!(transducer.psi(), psi);
         assert_eq!(transducer.min_except, min_except);
-        assert_eq!(transducer.states_by_signature, states_by_signature);
+        assert_eq!(transducer.states_by_signature(), states_by_signature);
     }
 
     #[test]
@@ -629,16 +1182,16 @@ mod tests {
         ]);
 
         assert_eq!(transducer.alphabet, alphabet);
-        assert_eq!(transducer.states, states);
-        assert_eq!(transducer.finality, finality);
+        assert_eq!(transducer.state_set(), states);
+        assert_eq!(transducer.finality(), finality);
         assert_eq!(transducer.init_state, init_state);
-        assert_eq!(transducer.delta, delta);
-        assert_eq!(transducer.delta_inv, delta_inv);
-        assert_eq!(transducer.lambda, lambda);
+        assert_eq!(transducer.delta(), delta);
+        assert_eq!(transducer.delta_inv(), delta_inv);
+        assert_eq!(transducer.lambda(), lambda);
         assert_eq!(transducer.iota, iota);
-        assert_eq!(transducer.psi, psi);
+        assert_eq!(transducer.psi(), psi);
         assert_eq!(transducer.min_except, min_except);
-        assert_eq!(transducer.states_by_signature, states_by_signature);
+        assert_eq!(transducer.states_by_signature(), states_by_signature);
     }
 
     #[test]
@@ -673,16 +1226,20 @@ mod tests {
         let psi = HashMap::from([(5, 0)]);
 
         assert_eq!(transducer.alphabet, example.alphabet);
-        assert_eq!(transducer.states, states);
-        assert_eq!(transducer.finality, finality);
+        assert_eq!(transducer.state_set(), states);
+        assert_eq!(transducer.finality(), finality);
         assert_eq!(transducer.init_state, example.init_state);
-        assert_eq!(transducer.delta, delta);
-        assert_eq!(transducer.delta_inv, delta_inv);
-        assert_eq!(transducer.lambda, lambda);
+        assert_eq!(transducer.delta(), delta);
+        assert_eq!(transducer.delta_inv(), delta_inv);
+        assert_eq!(transducer.lambda(), lambda);
         assert_eq!(transducer.iota, example.iota);
-        assert_eq!(transducer.psi, psi);
+        assert_eq!(transducer.psi(), psi);
         assert_eq!(transducer.min_except, example.min_except);
-        assert_eq!(transducer.states_by_signature, example.states_by_signature);
+        // The register is left untouched
+        assert_eq!(
+            transducer.register.iter().collect::<HashSet<_>>(),
+            example.register.iter().collect::<HashSet<_>>()
+        );
     }
 
     #[test]
@@ -724,16 +1281,16 @@ mod tests {
         let states_by_signature = HashMap::from([]);
 
         assert_eq!(transducer.alphabet, alphabet);
-        assert_eq!(transducer.states, states);
-        assert_eq!(transducer.finality, finality);
+        assert_eq!(transducer.state_set(), states);
+        assert_eq!(transducer.finality(), finality);
         assert_eq!(transducer.init_state, init_state);
-        assert_eq!(transducer.delta, delta);
-        assert_eq!(transducer.delta_inv, delta_inv);
-        assert_eq!(transducer.lambda, lambda);
+        assert_eq!(transducer.delta(), delta);
+        assert_eq!(transducer.delta_inv(), delta_inv);
+        assert_eq!(transducer.lambda(), lambda);
         assert_eq!(transducer.iota, iota);
-        assert_eq!(transducer.psi, psi);
+        assert_eq!(transducer.psi(), psi);
         assert_eq!(transducer.min_except, min_except);
-        assert_eq!(transducer.states_by_signature, states_by_signature);
+        assert_eq!(transducer.states_by_signature(), states_by_signature);
     }
 
     #[test]
@@ -783,16 +1340,156 @@ mod tests {
         ]);
 
         assert_eq!(transducer.alphabet, example.alphabet);
-        assert_eq!(transducer.states, states);
-        assert_eq!(transducer.finality, finality);
+        assert_eq!(transducer.state_set(), states);
+        assert_eq!(transducer.finality(), finality);
         assert_eq!(transducer.init_state, example.init_state);
-        assert_eq!(transducer.delta, delta);
-        assert_eq!(transducer.delta_inv, delta_inv);
-        assert_eq!(transducer.lambda, lambda);
+        assert_eq!(transducer.delta(), delta);
+        assert_eq!(transducer.delta_inv(), delta_inv);
+        assert_eq!(transducer.lambda(), lambda);
         assert_eq!(transducer.iota, example.iota);
-        assert_eq!(transducer.psi, psi);
+        assert_eq!(transducer.psi(), psi);
         assert_eq!(transducer.min_except, min_except);
-        assert_eq!(transducer.states_by_signature, states_by_signature);
+        assert_eq!(transducer.states_by_signature(), states_by_signature);
+    }
+
+    #[test]
+    fn replaces_the_output_of_an_existing_entry() {
+        let dictionary = vec![("cab", 15), ("cabab", 10), ("cad", 8), ("cbab", 3)];
+
+        for (word, output) in [("cab", 1), ("cab", 20), ("cbab", 30), ("cad", 8)] {
+            let mut transducer = Transducer::from_dictionary(dictionary.clone());
+            transducer.add_entry_out_of_order(word, output);
+
+            assert_eq!(transducer.validate(), Ok(()));
+            for (w, o) in &dictionary {
+                let expected = if *w == word { output } else { *o };
+                assert_eq!(transducer.output(&w.chars().collect()), expected);
+            }
+        }
+
+        let mut transducer = Transducer::from_dictionary(vec![("a", 11)]);
+        transducer.add_entry_out_of_order("a", 19);
+        assert_eq!(transducer.state_set(), BTreeSet::from([0, 1]));
+        assert_eq!(transducer.iota, 19);
+        assert_eq!(transducer.psi(), HashMap::from([(1, 0)]));
+    }
+
+    #[test]
+    fn validates_consistent_transducers() {
+        assert_eq!(example_transducer().validate(), Ok(()));
+        assert_eq!(example_transducer4().validate(), Ok(()));
+
+        let dictionary = vec![("cab", 15), ("cabab", 10), ("cad", 8), ("cbab", 3)];
+        let mut transducer = Transducer::from_dictionary(dictionary);
+        assert_eq!(transducer.validate(), Ok(()));
+
+        transducer.add_entry_out_of_order("ca", 9);
+        assert_eq!(transducer.validate(), Ok(()));
+
+        transducer.remove_entry_with_word("cabab");
+        assert_eq!(transducer.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validates_transducer_minimal_except_in_a_word() {
+        let mut transducer = Transducer::from_entry("baba", 10);
+        assert_eq!(transducer.validate(), Ok(()));
+
+        transducer.add_entry_in_order("bc", 15);
+        assert_eq!(transducer.validate(), Ok(()));
+    }
+
+    #[test]
+    fn reports_broken_invariants() {
+        let mut transducer = example_transducer();
+        transducer.state_mut(4).remove_predecessor('a', 6);
+        transducer.state_mut(4).final_output = Some(0);
+        transducer.state_mut(1).transition_mut('b').unwrap().output = 1;
+        transducer.state_mut(2).transitions.reverse();
+        transducer.states.push(Some(State::default()));
+        transducer.free.push(3);
+
+        let violations = transducer.validate().unwrap_err();
+
+        assert!(violations.contains(&InvariantViolation::MissingInverse {
+            from: 6,
+            ch: 'a',
+            to: 4
+        }));
+        assert!(violations.contains(&InvariantViolation::UnsortedTransitions(2)));
+        assert!(violations.contains(&InvariantViolation::InconsistentFreeList(3)));
+        assert!(violations.contains(&InvariantViolation::NonCanonical {
+            state: 1,
+            min_output: 1
+        }));
+        assert!(violations.contains(&InvariantViolation::Unreachable(7)));
+        assert!(violations.contains(&InvariantViolation::Dead(7)));
+        assert!(violations.contains(&InvariantViolation::MissingSignature(7)));
+        assert!(violations.contains(&InvariantViolation::StaleSignature(1)));
+        assert!(violations.contains(&InvariantViolation::StaleSignature(4)));
+    }
+
+    #[test]
+    fn minimises_a_transducer_from_scratch() {
+        let transducer = example_transducer2().minimised();
+        let expected_transducer = example_transducer();
+
+        assert_eq!(transducer.validate(), Ok(()));
+        assert_eq!(transducer.state_set(), expected_transducer.state_set());
+        assert_eq!(
+            transducer.finality().len(),
+            expected_transducer.finality().len()
+        );
+        assert_eq!(
+            transducer.get_number_of_transitions(),
+            expected_transducer.get_number_of_transitions()
+        );
+        assert_eq!(transducer.iota, expected_transducer.iota);
+        for (w, o) in [("cab", 15), ("cabab", 10), ("cad", 8), ("cbab", 3)] {
+            assert_eq!(transducer.output(&w.chars().collect()), o);
+        }
+    }
+
+    #[test]
+    fn canonicalises_while_minimising() {
+        let transducer = example_transducer3().minimised();
+
+        assert_eq!(transducer.validate(), Ok(()));
+        assert_eq!(transducer.state_set().len(), 6);
+        assert_eq!(transducer.iota, 10);
+        for (w, o) in [("cab", 15), ("cabab", 10), ("cabc", 12)] {
+            assert_eq!(transducer.output(&w.chars().collect()), o);
+        }
+    }
+
+    #[test]
+    fn checks_minimality() {
+        assert!(example_transducer().is_minimal());
+        assert!(!example_transducer2().is_minimal());
+
+        let dictionary = vec![("cab", 15), ("cabab", 10), ("cad", 8), ("cbab", 3)];
+        let mut transducer = Transducer::from_dictionary(dictionary);
+        let mutations = [
+            ("ca", Some(9)),
+            ("cabab", None),
+            ("cbab", Some(7)),
+            ("cbb", Some(3)),
+            ("ca", None),
+            ("ab", Some(40)),
+            ("cab", Some(2)),
+            ("cad", None),
+            ("cabd", Some(3)),
+        ];
+
+        for (word, output) in mutations {
+            match output {
+                Some(o) => transducer.add_entry_out_of_order(word, o),
+                None => {
+                    transducer.remove_entry_with_word(word);
+                }
+            }
+            assert!(transducer.is_minimal(), "not minimal after {:?}", word);
+        }
     }
 
     #[test]
@@ -839,16 +1536,16 @@ mod tests {
         ]);
 
         assert_eq!(transducer.alphabet, alphabet);
-        assert_eq!(transducer.states, states);
-        assert_eq!(transducer.finality, finality);
+        assert_eq!(transducer.state_set(), states);
+        assert_eq!(transducer.finality(), finality);
         assert_eq!(transducer.init_state, init_state);
-        assert_eq!(transducer.delta, delta);
-        assert_eq!(transducer.delta_inv, delta_inv);
-        assert_eq!(transducer.lambda, lambda);
+        assert_eq!(transducer.delta(), delta);
+        assert_eq!(transducer.delta_inv(), delta_inv);
+        assert_eq!(transducer.lambda(), lambda);
         assert_eq!(transducer.iota, iota);
-        assert_eq!(transducer.psi, psi);
+        assert_eq!(transducer.psi(), psi);
         assert_eq!(transducer.min_except, min_except);
-        assert_eq!(transducer.states_by_signature, states_by_signature);
+        assert_eq!(transducer.states_by_signature(), states_by_signature);
     }
 
     #[test]
@@ -917,16 +1614,16 @@ mod tests {
         ]);
 
         assert_eq!(transducer.alphabet, example.alphabet);
-        assert_eq!(transducer.states, states);
-        assert_eq!(transducer.finality, finality);
+        assert_eq!(transducer.state_set(), states);
+        assert_eq!(transducer.finality(), finality);
         assert_eq!(transducer.init_state, example.init_state);
-        assert_eq!(transducer.delta, delta);
-        assert_eq!(transducer.delta_inv, delta_inv);
-        assert_eq!(transducer.lambda, lambda);
+        assert_eq!(transducer.delta(), delta);
+        assert_eq!(transducer.delta_inv(), delta_inv);
+        assert_eq!(transducer.lambda(), lambda);
         assert_eq!(transducer.iota, example.iota);
-        assert_eq!(transducer.psi, psi);
+        assert_eq!(transducer.psi(), psi);
         assert_eq!(transducer.min_except, min_except);
-        assert_eq!(transducer.states_by_signature, states_by_signature);
+        assert_eq!(transducer.states_by_signature(), states_by_signature);
     }
 
     #[test]
@@ -936,33 +1633,608 @@ mod tests {
         transducer.reduce_to_epsilon();
 
         assert_eq!(transducer.alphabet, expected.alphabet);
-        assert_eq!(transducer.states, expected.states);
-        assert_eq!(transducer.finality, expected.finality);
+        assert_eq!(transducer.state_set(), expected.state_set());
+        assert_eq!(transducer.finality(), expected.finality());
         assert_eq!(transducer.init_state, expected.init_state);
-        assert_eq!(transducer.delta, expected.delta);
-        assert_eq!(transducer.delta_inv, expected.delta_inv);
-        assert_eq!(transducer.lambda, expected.lambda);
+        assert_eq!(transducer.delta(), expected.delta());
+        assert_eq!(transducer.delta_inv(), expected.delta_inv());
+        assert_eq!(transducer.lambda(), expected.lambda());
         assert_eq!(transducer.iota, expected.iota);
-        assert_eq!(transducer.psi, expected.psi);
+        assert_eq!(transducer.psi(), expected.psi());
         assert_eq!(transducer.min_except, expected.min_except);
-        assert_eq!(transducer.states_by_signature, expected.states_by_signature);
+        assert_eq!(
+            transducer.states_by_signature(),
+            expected.states_by_signature()
+        );
     }
 
     #[test]
     fn adds_delta_and_lambda_transitions() {
         let mut transducer = Transducer::from_entry("cab", 15);
+        assert_eq!(transducer.add_new_states(2), vec![4, 5]);
+
+        transducer.add_delta_transition(3, 'a', 4, 123);
 
-        transducer.add_delta_transition(3, 'a', 4);
-        add_to_or_insert(&mut transducer.lambda, 3, 'a', 123);
+        assert_eq!(transducer.delta()[&3][&'a'], 4);
+        assert_eq!(transducer.lambda()[&3][&'a'], 123);
 
-        assert_eq!(transducer.delta[&3][&'a'], 4);
-        assert_eq!(transducer.lambda[&3][&'a'], 123);
+        transducer.add_delta_transition(3, 'b', 5, 321);
 
-        transducer.add_delta_transition(3, 'b', 5);
-        add_to_or_insert(&mut transducer.lambda, 3, 'b', 321);
+        assert_eq!(transducer.delta()[&3][&'b'], 5);
+        assert_eq!(transducer.lambda()[&3][&'b'], 321);
 
-        assert_eq!(transducer.delta[&3][&'b'], 5);
-        assert_eq!(transducer.lambda[&3][&'b'], 321);
+        // Overwriting a transition moves it to the predecessors of the new target
+        transducer.add_delta_transition(3, 'a', 5, 7);
+
+        assert_eq!(transducer.delta()[&3][&'a'], 5);
+        assert_eq!(transducer.lambda()[&3][&'a'], 7);
+        assert_eq!(transducer.delta_inv().get(&4), None);
+        assert_eq!(
+            transducer.delta_inv()[&5],
+            HashSet::from([('a', 3), ('b', 3)])
+        );
+    }
+
+    #[test]
+    fn reuses_the_ids_of_deleted_states() {
+        let mut transducer = example_transducer2();
+        transducer.reduce_except_by_one();
+
+        assert_eq!(transducer.free, vec![8]);
+        assert_eq!(transducer.get_number_of_states(), 8);
+        assert_eq!(transducer.add_new_states(2), vec![8, 9]);
+        assert!(transducer.free.is_empty());
+    }
+
+    #[test]
+    fn compacts_the_state_ids() {
+        let mut transducer =
+            Transducer::from_dictionary(vec![("cab", 15), ("cabab", 10), ("cad", 8), ("cbab", 3)]);
+        transducer.remove_entry_with_word("cabab");
+        transducer.remove_entry_with_word("cbab");
+        let entries: Vec<(String, i64)> = transducer.entries().collect();
+
+        assert!(transducer.get_number_of_released_ids() > 0);
+
+        transducer.compact();
+
+        assert_eq!(transducer.get_number_of_released_ids(), 0);
+        assert_eq!(transducer.states.len(), transducer.get_number_of_states());
+        assert_eq!(transducer.state_set(), BTreeSet::from([0, 1, 2, 3]));
+        assert_eq!(transducer.delta()[&0][&'c'], 1);
+        assert_eq!(transducer.delta()[&1][&'a'], 2);
+        assert_eq!(transducer.alphabet, HashSet::from(['a', 'b', 'c', 'd']));
+        assert_eq!(transducer.entries().collect::<Vec<_>>(), entries);
+        assert_eq!(transducer.validate(), Ok(()));
+        assert!(transducer.is_minimal());
+
+        transducer.add_entry_out_of_order("cbab", 3);
+
+        assert_eq!(transducer.get("cbab"), Some(3));
+        assert_eq!(transducer.validate(), Ok(()));
+    }
+
+    #[test]
+    fn compacts_automatically_over_the_threshold() {
+        let dictionary = vec![("cab", 15), ("cabab", 10), ("cad", 8), ("cbab", 3)];
+
+        let mut transducer = Transducer::from_dictionary(dictionary.clone());
+        transducer.remove_batch(["cabab", "cbab"]);
+
+        assert!(transducer.get_number_of_released_ids() > 0);
+
+        let mut transducer = Transducer::from_dictionary(dictionary);
+        transducer.set_compaction_threshold(Some(0.0));
+        transducer.remove_batch(["cabab", "cbab"]);
+
+        assert_eq!(transducer.get_number_of_released_ids(), 0);
+        assert_eq!(transducer.states.len(), transducer.get_number_of_states());
+        assert_eq!(transducer.validate(), Ok(()));
+
+        // The threshold survives the transducer becoming empty
+        transducer.remove_batch(["cab", "cad"]);
+        transducer.add_entry_out_of_order("cab", 1);
+        transducer.add_entry_out_of_order("ca", 2);
+        transducer.remove_entry_with_word("cab");
+
+        assert_eq!(transducer.get_number_of_released_ids(), 0);
+        assert_eq!(transducer.get("ca"), Some(2));
+    }
+
+    #[test]
+    fn registers_states_with_colliding_hashes() {
+        let mut register = Register::default();
+        register.insert(7, 1, |_| false);
+        register.insert(7, 2, |_| false);
+
+        assert_eq!(register.find(7, |q| q == 2), Some(2));
+        assert_eq!(register.find(7, |_| false), None);
+        assert_eq!(register.find(8, |_| true), None);
+
+        // An equivalent state replaces the registered one
+        register.insert(7, 3, |q| q == 1);
+        assert_eq!(register.find(7, |q| q == 1), None);
+        assert_eq!(register.find(7, |q| q == 3), Some(3));
+
+        assert_eq!(register.remove(7, |q| q == 2), Some(2));
+        assert_eq!(register.remove(7, |q| q == 2), None);
+        assert_eq!(register.iter().collect::<Vec<_>>(), vec![(7, 3)]);
+    }
+
+    #[test]
+    fn reports_memory_usage() {
+        let mut transducer =
+            Transducer::from_dictionary(vec![("cab", 15), ("cabab", 10), ("cad", 8), ("cbab", 3)]);
+        let stats = transducer.memory_stats();
+
+        let mut saved = Vec::new();
+        transducer.save(&mut saved).unwrap();
+
+        assert_eq!(stats.serialised, saved.len());
+        assert!(stats.states >= 7 * size_of::<Option<State>>());
+        assert!(stats.transitions >= 7 * size_of::<Transition>());
+        assert!(stats.predecessors > 0);
+        assert!(stats.register > 0);
+        assert!(stats.alphabet > 0);
+        assert!(stats.total() > stats.states + stats.transitions + stats.register);
+        assert!(stats.to_string().contains("Total: "));
+
+        transducer.remove_entry_with_word("cbab");
+        let stats = transducer.memory_stats();
+
+        assert!(stats.free_list > 0);
+        saved.clear();
+        transducer.save(&mut saved).unwrap();
+        assert_eq!(stats.serialised, saved.len());
+    }
+
+    #[test]
+    fn reserves_about_one_state_per_entry() {
+        let words: Vec<String> = (0..1000).map(|i| format!("w{:04}", i)).collect();
+        let transducer = Transducer::from_sorted_iter(words.iter().map(|w| (w, 1)));
+        let stats = transducer.memory_stats();
+
+        assert!(stats.states < 2 * 1000 * size_of::<Option<State>>());
+    }
+
+    #[test]
+    fn builds_key_ranges_in_parallel() {
+        let entries = vec![
+            ("apple", 3),
+            ("apples", 4),
+            ("banana", 3),
+            ("cab", 15),
+            ("cabab", 10),
+            ("cad", 8),
+            ("cbab", 3),
+            ("dab", 13),
+        ];
+        let transducer = Transducer::from_sorted_slice_parallel(&entries, 4);
+        let sequential = Transducer::from_sorted_iter(entries.clone());
+
+        assert_eq!(
+            transducer.entries().collect::<Vec<_>>(),
+            sequential.entries().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            transducer.get_number_of_states(),
+            sequential.get_number_of_states()
+        );
+        assert_eq!(transducer.get_initial_output(), 3);
+        assert_eq!(transducer.validate(), Ok(()));
+        assert!(transducer.is_minimal());
+
+        // The extended transducer stays minimal
+        let mut transducer = transducer;
+        transducer.add_entry_out_of_order("bab", 1);
+        assert_eq!(transducer.get("bab"), Some(1));
+        assert_eq!(transducer.validate(), Ok(()));
+    }
+
+    #[test]
+    fn reports_invalid_entries_of_parallel_construction() {
+        let policy = DuplicatePolicy::Reject;
+        let unsorted = vec![("a", 1), ("b", 2), ("c", 3), ("ba", 4), ("d", 5)];
+        let duplicate = vec![("a", 1), ("b", 2), ("c", 3), ("c", 4), ("d", 5)];
+        let empty: Vec<(&str, i64)> = vec![];
+
+        assert_eq!(
+            Transducer::try_from_sorted_slice_parallel(&unsorted, policy, 3).err(),
+            Some(BuildError::OutOfOrder {
+                line: 4,
+                word: String::from("ba"),
+                previous: String::from("c")
+            })
+        );
+        assert_eq!(
+            Transducer::try_from_sorted_slice_parallel(&duplicate, policy, 3).err(),
+            Some(BuildError::Duplicate {
+                line: 4,
+                word: String::from("c")
+            })
+        );
+        assert_eq!(
+            Transducer::try_from_sorted_slice_parallel(&empty, policy, 3).err(),
+            Some(BuildError::Empty)
+        );
+
+        let summed =
+            Transducer::try_from_sorted_slice_parallel(&duplicate, DuplicatePolicy::Sum, 3);
+        assert_eq!(summed.unwrap().get("c"), Some(7));
+    }
+
+    #[test]
+    fn unites_two_transducers() {
+        let left = Transducer::from_dictionary(vec![("cab", 15), ("cabab", 10), ("cad", 8)]);
+        let right = Transducer::from_dictionary(vec![("ca", 2), ("cad", 4), ("cbab", 3)]);
+
+        let union = Transducer::union(&left, &right, ConflictPolicy::Sum);
+
+        assert_eq!(
+            union.entries().collect::<Vec<_>>(),
+            vec![
+                (String::from("ca"), 2),
+                (String::from("cab"), 15),
+                (String::from("cabab"), 10),
+                (String::from("cad"), 12),
+                (String::from("cbab"), 3),
+            ]
+        );
+        assert_eq!(union.validate(), Ok(()));
+        assert!(union.is_minimal());
+
+        assert_eq!(
+            Transducer::union(&left, &right, ConflictPolicy::Left).get("cad"),
+            Some(8)
+        );
+        assert_eq!(
+            Transducer::union(&left, &right, ConflictPolicy::Min).get("cad"),
+            Some(4)
+        );
+        assert_eq!("max".parse(), Ok(ConflictPolicy::Max));
+        assert!("first".parse::<ConflictPolicy>().is_err());
+
+        // The union with an empty transducer is an equal transducer
+        let empty = Transducer::empty();
+        let union = Transducer::union(&empty, &right, ConflictPolicy::Left);
+        assert_eq!(union.get_number_of_states(), right.get_number_of_states());
+        assert!(Transducer::union(&empty, &empty, ConflictPolicy::Left).is_empty());
+    }
+
+    #[test]
+    fn intersects_and_subtracts_two_transducers() {
+        let left = Transducer::from_dictionary(vec![("cab", 15), ("cabab", 10), ("cad", 8)]);
+        let right = Transducer::from_dictionary(vec![("ca", 2), ("cad", 4), ("cbab", 3)]);
+
+        let intersection = Transducer::intersection(&left, &right, |l, r| l - r);
+
+        assert_eq!(
+            intersection.entries().collect::<Vec<_>>(),
+            vec![(String::from("cad"), 4)]
+        );
+        assert_eq!(intersection.get_number_of_states(), 4);
+        assert_eq!(intersection.validate(), Ok(()));
+
+        let difference = Transducer::difference(&left, &right);
+
+        assert_eq!(
+            difference.entries().collect::<Vec<_>>(),
+            vec![(String::from("cab"), 15), (String::from("cabab"), 10)]
+        );
+        assert_eq!(difference.validate(), Ok(()));
+        assert!(difference.is_minimal());
+
+        let disjoint = Transducer::from_entry("x", 1);
+        assert!(Transducer::intersection(&left, &disjoint, |l, _| l).is_empty());
+        assert!(Transducer::difference(&left, &left).is_empty());
+        assert_eq!(
+            Transducer::difference(&left, &disjoint).get_number_of_states(),
+            left.get_number_of_states()
+        );
+    }
+
+    #[test]
+    fn compares_the_functions_of_two_transducers() {
+        let dictionary = vec![("cab", 15), ("cabab", 10), ("cad", 8), ("cbab", 3)];
+        let transducer = Transducer::from_dictionary(dictionary.clone());
+
+        // The same function with other state ids
+        let mut renumbered = Transducer::empty();
+        for (w, o) in dictionary.iter().rev() {
+            renumbered.add_entry_out_of_order(w, *o);
+        }
+        assert_ne!(transducer.delta(), renumbered.delta());
+        assert!(transducer.equivalent(&renumbered));
+        assert!(transducer == renumbered);
+        assert_eq!(transducer.counterexample(&renumbered), None);
+
+        renumbered.add_entry_out_of_order("cad", 9);
+        assert_eq!(
+            transducer.counterexample(&renumbered),
+            Some(String::from("cad"))
+        );
+
+        renumbered.add_entry_out_of_order("cad", 8);
+        renumbered.add_entry_out_of_order("cabb", 8);
+        assert_eq!(
+            renumbered.counterexample(&transducer),
+            Some(String::from("cabb"))
+        );
+        assert!(transducer != renumbered);
+
+        let empty = Transducer::empty();
+        assert_eq!(empty.counterexample(&transducer), Some(String::from("cab")));
+        assert!(empty == Transducer::empty());
+    }
+
+    #[test]
+    fn finds_a_shortest_counterexample() {
+        let left = Transducer::from_dictionary(vec![("ab", 1), ("c", 1)]);
+        let right = Transducer::from_dictionary(vec![("c", 2)]);
+
+        // "ab" is only in the left transducer, but "c" is shorter
+        assert_eq!(left.counterexample(&right), Some(String::from("c")));
+        assert_eq!(right.counterexample(&left), Some(String::from("c")));
+
+        let left = Transducer::from_dictionary(vec![("ab", 1), ("cde", 1)]);
+        let right = Transducer::from_dictionary(vec![("cde", 2)]);
+        assert_eq!(left.counterexample(&right), Some(String::from("ab")));
+    }
+
+    #[test]
+    fn diffs_two_transducers() {
+        let old = Transducer::from_dictionary(vec![("cab", 15), ("cabab", 10), ("cad", 8)]);
+        let new = Transducer::from_dictionary(vec![("ca", 2), ("cab", 15), ("cad", 4)]);
+
+        assert_eq!(
+            Transducer::diff(&old, &new).collect::<Vec<_>>(),
+            vec![
+                DiffEntry::Added(String::from("ca"), 2),
+                DiffEntry::Removed(String::from("cabab"), 10),
+                DiffEntry::Changed {
+                    word: String::from("cad"),
+                    old: 8,
+                    new: 4
+                },
+            ]
+        );
+        assert_eq!(Transducer::diff(&old, &old).next(), None);
+        assert_eq!(
+            Transducer::diff(&Transducer::empty(), &old)
+                .map(|entry| entry.word().to_string())
+                .collect::<Vec<_>>(),
+            vec!["cab", "cabab", "cad"]
+        );
+    }
+
+    #[test]
+    fn skips_shared_subgraphs_in_a_diff() {
+        let old = Transducer::from_dictionary(vec![("ab", 1), ("abc", 2), ("b", 3), ("bc", 4)]);
+        let new = Transducer::from_dictionary(vec![("ab", 1), ("abc", 2), ("b", 3), ("bd", 4)]);
+        let mut diff = Transducer::diff(&old, &new);
+
+        assert_eq!(diff.next(), Some(DiffEntry::Removed(String::from("bc"), 4)));
+        assert_eq!(diff.next(), Some(DiffEntry::Added(String::from("bd"), 4)));
+        assert_eq!(diff.next(), None);
+
+        // The states after "a" are equivalent, so their words were not visited
+        let after_a = |t: &Transducer| t.state(t.init_state).next('a').unwrap();
+        assert_eq!(
+            diff.equivalent.get(&(after_a(&old), after_a(&new))),
+            Some(&true)
+        );
+    }
+
+    #[test]
+    fn composes_a_normalisation_with_a_lookup() {
+        let lemmas = StringTransducer::from_pairs(vec![
+            ("walks", "walk"),
+            ("walked", "walk"),
+            ("walking", "walk"),
+            ("talked", "talk"),
+            ("saw", "see"),
+            ("saw", "saw"),
+            ("ran", "run"),
+        ]);
+        assert_eq!(lemmas.get("saw"), vec!["saw", "see"]);
+        assert_eq!(lemmas.get("walking"), vec!["walk"]);
+        assert!(lemmas.get("walk").is_empty());
+
+        // "saw" is ambiguous, but only one of its lemmas has an id
+        let ids =
+            Transducer::from_dictionary(vec![("run", 3), ("see", 5), ("talk", 7), ("walk", 9)]);
+        let composition = Transducer::compose(&lemmas, &ids).unwrap();
+
+        assert_eq!(
+            composition.entries().collect::<Vec<_>>(),
+            vec![
+                (String::from("ran"), 3),
+                (String::from("saw"), 5),
+                (String::from("talked"), 7),
+                (String::from("walked"), 9),
+                (String::from("walking"), 9),
+                (String::from("walks"), 9),
+            ]
+        );
+        assert_eq!(composition.validate(), Ok(()));
+        assert!(composition.is_minimal());
+        assert_eq!(
+            composition.get_number_of_states(),
+            composition.minimised().get_number_of_states()
+        );
+
+        let nothing = StringTransducer::from_pairs(Vec::<(&str, &str)>::new());
+        assert!(Transducer::compose(&nothing, &ids).unwrap().is_empty());
+        let unknown = StringTransducer::from_pairs(vec![("went", "go")]);
+        assert!(Transducer::compose(&unknown, &ids).unwrap().is_empty());
+    }
+
+    #[test]
+    fn fails_to_compose_ambiguous_outputs() {
+        let lemmas =
+            StringTransducer::from_pairs(vec![("saw", "see"), ("saw", "saw"), ("sees", "see")]);
+        let ids = Transducer::from_dictionary(vec![("saw", 4), ("see", 5)]);
+
+        let Err(error) = Transducer::compose(&lemmas, &ids) else {
+            panic!("The composition must fail!");
+        };
+        assert_eq!(
+            error,
+            CompositionError {
+                word: String::from("saw"),
+                outputs: vec![4, 5],
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "the composition is not subsequential: \"saw\" has the outputs [4, 5]"
+        );
+    }
+
+    #[test]
+    fn inverts_an_injective_dictionary() {
+        let transducer = Transducer::from_dictionary(vec![
+            ("cab", 15),
+            ("cabab", 10),
+            ("cad", 8),
+            ("cbab", 300),
+        ]);
+
+        let inverse = transducer.invert(KeyEncoding::Decimal).unwrap();
+        assert_eq!(
+            inverse.entries().collect::<Vec<_>>(),
+            vec![
+                (String::from("10"), 1),
+                (String::from("15"), 0),
+                (String::from("300"), 3),
+                (String::from("8"), 2),
+            ]
+        );
+        assert_eq!(inverse.validate(), Ok(()));
+        assert!(inverse.is_minimal());
+
+        let words = transducer.invert_to_words(KeyEncoding::Decimal).unwrap();
+        assert_eq!(words.get("300"), vec!["cbab"]);
+        assert!(words.get("30").is_empty());
+
+        // The keys "1" and "2" lead to equivalent states once the words are pushed
+        let words = Transducer::from_dictionary(vec![("ab", 1), ("cb", 2)])
+            .invert_to_words(KeyEncoding::Decimal)
+            .unwrap();
+        assert_eq!(words.get_number_of_states(), 2);
+        assert_eq!(words.get("1"), vec!["ab"]);
+        assert_eq!(words.get("2"), vec!["cb"]);
+
+        // Fixed-width keys sort like the outputs
+        let encoding = KeyEncoding::FixedWidth(2);
+        assert_eq!(encoding.encode(300), Some(String::from("\u{81}\u{2c}")));
+        assert_eq!(encoding.encode(-1), Some(String::from("\u{7f}\u{ff}")));
+        assert_eq!(encoding.encode(-32769), None);
+        let inverse = transducer.invert(encoding).unwrap();
+        assert_eq!(
+            inverse.entries().map(|(_, rank)| rank).collect::<Vec<_>>(),
+            vec![2, 1, 0, 3]
+        );
+        assert_eq!(inverse.get(&encoding.encode(15).unwrap()), Some(0));
+
+        assert!(Transducer::empty()
+            .invert(KeyEncoding::Decimal)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn fails_to_invert_a_non_injective_dictionary() {
+        let transducer = Transducer::from_dictionary(vec![("cab", 15), ("cad", 8), ("cbab", 15)]);
+
+        assert_eq!(
+            transducer.invert(KeyEncoding::Decimal).err(),
+            Some(InversionError::NotInjective {
+                output: 15,
+                word: String::from("cab"),
+                other: String::from("cbab"),
+            })
+        );
+        assert_eq!(
+            transducer.invert(KeyEncoding::FixedWidth(1)).err(),
+            Some(InversionError::NotInjective {
+                output: 15,
+                word: String::from("cab"),
+                other: String::from("cbab"),
+            })
+        );
+
+        let wide = Transducer::from_entry("cab", 256);
+        assert_eq!(
+            wide.invert(KeyEncoding::FixedWidth(1)).err(),
+            Some(InversionError::TooWide {
+                output: 256,
+                width: 1
+            })
+        );
+        assert!(wide.invert(KeyEncoding::FixedWidth(9)).is_ok());
+    }
+
+    #[test]
+    fn keeps_the_ranks_of_words() {
+        let mut transducer = Transducer::from_sorted_words(["cab", "cabab", "cad", "cbab"]);
+        assert!(transducer.is_ranked());
+        assert_eq!(transducer.get_initial_output(), 0);
+        assert_eq!(transducer.get("cad"), Some(2));
+
+        assert_eq!(transducer.rank_of("cab"), 0);
+        assert_eq!(transducer.rank_of("caba"), 1);
+        assert_eq!(transducer.rank_of("cc"), 4);
+        assert_eq!(Transducer::empty().rank_of("a"), 0);
+
+        // The output is ignored and the following ranks shift
+        transducer.add_entry_out_of_order("caba", 100);
+        assert_eq!(
+            transducer.entries().collect::<Vec<_>>(),
+            vec![
+                (String::from("cab"), 0),
+                (String::from("caba"), 1),
+                (String::from("cabab"), 2),
+                (String::from("cad"), 3),
+                (String::from("cbab"), 4),
+            ]
+        );
+
+        assert_eq!(transducer.remove_entry_with_word("cab"), Some(0));
+        assert_eq!(transducer.get("cbab"), Some(3));
+        assert_eq!(transducer.validate(), Ok(()));
+
+        // The rank mode is saved with the transducer
+        let mut bytes = Vec::new();
+        transducer.save(&mut bytes).unwrap();
+        let mut loaded = Transducer::load(bytes.as_slice()).unwrap();
+        assert!(loaded.is_ranked());
+        loaded.add_entry_out_of_order("a", 100);
+        assert_eq!(loaded.get("cbab"), Some(4));
+        assert_eq!(
+            transducer.get_number_of_states(),
+            transducer.minimised().get_number_of_states()
+        );
+
+        // Ranks can be turned on for outputs that are already ranks
+        let mut dictionary = Transducer::from_dictionary(vec![("a", 0), ("b", 1)]);
+        dictionary.set_rank_mode(true);
+        dictionary.add_entry_out_of_order("aa", 0);
+        assert_eq!(dictionary.get("b"), Some(2));
+
+        assert_eq!(
+            Transducer::try_from_sorted_words(["a", "a"]).err(),
+            Some(BuildError::Duplicate {
+                line: 2,
+                word: String::from("a")
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The outputs must be the ranks of the words!")]
+    fn rejects_the_rank_mode_for_other_outputs() {
+        Transducer::from_dictionary(vec![("a", 1), ("b", 0)]).set_rank_mode(true);
     }
 
     #[test]
@@ -972,10 +2244,10 @@ mod tests {
     }
 
     #[test]
-    fn lambda_star() {
+    fn prefix_output() {
         let transducer = example_transducer();
-        assert_eq!(transducer.lambda_star(&vec!['c', 'a', 'b', 'a']), 7);
-        assert_eq!(transducer.lambda_star(&vec!['c', 'a']), 5);
+        assert_eq!(transducer.prefix_output(&vec!['c', 'a', 'b', 'a']), 10);
+        assert_eq!(transducer.prefix_output(&vec!['c', 'a']), 8);
     }
 
     #[test]
@@ -996,6 +2268,139 @@ mod tests {
 
     // Helper functions
     ///////////////////
+
+    /** The transducer described by maps from the state ids, like in the papers */
+    struct Maps {
+        alphabet: HashSet<char>,
+        states: BTreeSet<usize>,
+        finality: BTreeSet<usize>,
+        init_state: usize,
+        delta: HashMap<usize, HashMap<char, usize>>,
+        delta_inv: HashMap<usize, HashSet<(char, usize)>>,
+        lambda: HashMap<usize, HashMap<char, i64>>,
+        iota: i64,
+        psi: HashMap<usize, i64>,
+        min_except: Vec<char>,
+        states_by_signature: HashMap<Signature, usize>,
+    }
+
+    /** The final output of a state together with its outgoing (char, state, output) transitions */
+    type Signature = (Option<i64>, BTreeSet<(char, usize, i64)>);
+
+    /** Lays out the maps in the arena. The ids missing from states are released */
+    fn from_maps(maps: Maps) -> Transducer {
+        let n = maps.states.last().map_or(0, |q| q + 1);
+        let mut states: Vec<Option<State>> = (0..n)
+            .map(|q| maps.states.contains(&q).then(State::default))
+            .collect();
+        let free = (0..n).filter(|q| !maps.states.contains(q)).collect();
+
+        for (q, trans) in &maps.delta {
+            let state = states[*q].as_mut().unwrap();
+            for (ch, to) in trans {
+                let output = maps.lambda[q][ch];
+                state.set_transition(Transition {
+                    ch: *ch,
+                    to: *to,
+                    output,
+                });
+            }
+        }
+
+        for (q, preds) in &maps.delta_inv {
+            let mut preds: Vec<(char, usize)> = preds.iter().copied().collect();
+            preds.sort();
+            states[*q].as_mut().unwrap().predecessors = preds;
+        }
+
+        for q in &maps.finality {
+            states[*q].as_mut().unwrap().final_output = Some(maps.psi[q]);
+        }
+
+        let mut register = Register::default();
+        for q in maps.states_by_signature.values() {
+            let hash = states[*q].as_ref().unwrap().signature_hash();
+            register.insert(hash, *q, |_| false);
+        }
+
+        return Transducer {
+            alphabet: maps.alphabet,
+            states,
+            free,
+            init_state: maps.init_state,
+            iota: maps.iota,
+            min_except: maps.min_except,
+            register,
+            compaction_threshold: None,
+            ranked: false,
+        };
+    }
+
+    /** Views of the arena as the maps from the state ids */
+    impl Transducer {
+        /** The register with the signatures of the registered states */
+        fn states_by_signature(&self) -> HashMap<Signature, usize> {
+            return self
+                .register
+                .iter()
+                .filter_map(|(_, q)| {
+                    let state = self.states[q].as_ref()?;
+                    let transitions = state
+                        .transitions
+                        .iter()
+                        .map(|t| (t.ch, t.to, t.output))
+                        .collect();
+                    Some(((state.final_output, transitions), q))
+                })
+                .collect();
+        }
+
+        fn state_set(&self) -> BTreeSet<usize> {
+            return self.live_states().map(|(q, _)| q).collect();
+        }
+
+        fn finality(&self) -> BTreeSet<usize> {
+            return self
+                .live_states()
+                .filter(|(_, state)| state.is_final())
+                .map(|(q, _)| q)
+                .collect();
+        }
+
+        fn delta(&self) -> HashMap<usize, HashMap<char, usize>> {
+            return self
+                .live_states()
+                .filter(|(_, state)| !state.transitions.is_empty())
+                .map(|(q, state)| (q, state.transitions.iter().map(|t| (t.ch, t.to)).collect()))
+                .collect();
+        }
+
+        fn delta_inv(&self) -> HashMap<usize, HashSet<(char, usize)>> {
+            return self
+                .live_states()
+                .filter(|(_, state)| !state.predecessors.is_empty())
+                .map(|(q, state)| (q, state.predecessors.iter().copied().collect()))
+                .collect();
+        }
+
+        fn lambda(&self) -> HashMap<usize, HashMap<char, i64>> {
+            return self
+                .live_states()
+                .filter(|(_, state)| !state.transitions.is_empty())
+                .map(|(q, state)| {
+                    let outputs = state.transitions.iter().map(|t| (t.ch, t.output));
+                    (q, outputs.collect())
+                })
+                .collect();
+        }
+
+        fn psi(&self) -> HashMap<usize, i64> {
+            return self
+                .live_states()
+                .filter_map(|(q, state)| state.final_output.map(|o| (q, o)))
+                .collect();
+        }
+    }
     fn example_transducer() -> Transducer {
         // dictionary := [("cab", 15), ("cabab", 10), ("cad", 8), ("cbab", 3)]
         let alphabet = HashSet::from(['a', 'b', 'c', 'd']);
@@ -1039,7 +2444,7 @@ mod tests {
             ((None, BTreeSet::from([('a', 4, 0)])), 6),
         ]);
 
-        return Transducer {
+        return from_maps(Maps {
             alphabet,
             states,
             finality,
@@ -1051,7 +2456,7 @@ mod tests {
             psi,
             min_except,
             states_by_signature,
-        };
+        });
     }
 
     fn example_transducer2() -> Transducer {
@@ -1100,7 +2505,7 @@ mod tests {
             ((Some(0), BTreeSet::from([])), 5),
         ]);
 
-        return Transducer {
+        return from_maps(Maps {
             alphabet,
             states,
             finality,
@@ -1112,7 +2517,7 @@ mod tests {
             psi,
             min_except,
             states_by_signature,
-        };
+        });
     }
 
     fn example_transducer3() -> Transducer {
@@ -1148,7 +2553,7 @@ mod tests {
         let min_except = vec!['c', 'a', 'b', 'a'];
         let states_by_signature = HashMap::from([((Some(0), BTreeSet::from([])), 5)]);
 
-        return Transducer {
+        return from_maps(Maps {
             alphabet,
             states,
             finality,
@@ -1160,7 +2565,7 @@ mod tests {
             psi,
             min_except,
             states_by_signature,
-        };
+        });
     }
 
     fn example_transducer4() -> Transducer {
@@ -1202,7 +2607,7 @@ mod tests {
             ((Some(0), BTreeSet::from([])), 5),
         ]);
 
-        return Transducer {
+        return from_maps(Maps {
             alphabet,
             states,
             finality,
@@ -1214,6 +2619,6 @@ mod tests {
             psi,
             min_except,
             states_by_signature,
-        };
+        });
     }
 }